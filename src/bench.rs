@@ -0,0 +1,137 @@
+//! Benchmark harness timing strategies against the same measurements file.
+
+use std::fs;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+
+use crate::{StationStats, Strategy};
+
+/// How many times to run every strategy.
+#[derive(Debug, Clone, Copy)]
+pub struct BenchConfig {
+    pub warmup: usize,
+    pub runs: usize,
+    pub threads: usize,
+}
+
+/// Machine-readable result of a benchmark, stable enough to diff across commits.
+#[derive(Debug, Serialize)]
+pub struct BenchReport {
+    pub version: &'static str,
+    pub timestamp: u64,
+    pub label: Option<String>,
+    pub file: String,
+    pub bytes: u64,
+    pub rows: u64,
+    pub stations: usize,
+    pub threads: usize,
+    pub warmup: usize,
+    pub runs: usize,
+    pub results: Vec<StrategyResult>,
+}
+
+/// Wall times of one strategy, in seconds.
+#[derive(Debug, Serialize)]
+pub struct StrategyResult {
+    pub strategy: &'static str,
+    pub min: f64,
+    pub median: f64,
+    pub mean: f64,
+    pub stddev: f64,
+    pub max: f64,
+    pub mb_per_second: f64,
+    pub rows_per_second: f64,
+    pub samples: Vec<f64>,
+}
+
+impl StrategyResult {
+    fn new(strategy: Strategy, durations: &[Duration], bytes: u64, rows: u64) -> Self {
+        let samples: Vec<f64> = durations.iter().map(Duration::as_secs_f64).collect();
+        let mut sorted = samples.clone();
+        sorted.sort_by(f64::total_cmp);
+
+        let runs = sorted.len();
+        let median = match runs % 2 {
+            0 => (sorted[runs / 2 - 1] + sorted[runs / 2]) / 2.,
+            _ => sorted[runs / 2],
+        };
+        let mean = sorted.iter().sum::<f64>() / runs as f64;
+        let variance = match runs {
+            1 => 0.,
+            _ => sorted.iter().map(| sample | (sample - mean).powi(2)).sum::<f64>() / (runs - 1) as f64,
+        };
+        Self {
+            strategy: strategy.name(),
+            min: sorted[0],
+            median,
+            mean,
+            stddev: variance.sqrt(),
+            max: sorted[runs - 1],
+            mb_per_second: bytes as f64 / 1e6 / median,
+            rows_per_second: rows as f64 / median,
+            samples,
+        }
+    }
+}
+
+/// Time every strategy on `file_path`, failing if any of them disagrees with the first one.
+pub fn run(
+    file_path: &str,
+    strategies: &[Strategy],
+    config: BenchConfig,
+    label: Option<String>,
+) -> Result<BenchReport> {
+    if strategies.is_empty() || config.runs == 0 {
+        bail!("nothing to benchmark, need at least one strategy and one run");
+    }
+    let bytes = fs::metadata(file_path)
+        .with_context(|| format!("failed to read {file_path}"))?
+        .len();
+
+    let mut expected: Option<StationStats> = None;
+    let mut results = Vec::with_capacity(strategies.len());
+    for &strategy in strategies {
+        let aggregator = strategy.aggregator(config.threads);
+        let mut durations = Vec::with_capacity(config.runs);
+        for run in 0..config.warmup + config.runs {
+            let timer = Instant::now();
+            let stats = aggregator
+                .aggregate(file_path)
+                .with_context(|| format!("{} failed on {file_path}", strategy.name()))?;
+            let elapsed = timer.elapsed();
+
+            if run >= config.warmup {
+                durations.push(elapsed);
+            }
+            match &expected {
+                Some(expected) if *expected != stats => {
+                    bail!("{} disagrees with {}", strategy.name(), strategies[0].name())
+                },
+                Some(_) => {},
+                None => expected = Some(stats),
+            }
+        }
+        results.push((strategy, durations));
+    }
+
+    let expected = expected.unwrap_or_default();
+    let rows = expected.iter().map(| station | station.count()).sum();
+    Ok(BenchReport {
+        version: env!("CARGO_PKG_VERSION"),
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        label,
+        file: file_path.to_owned(),
+        bytes,
+        rows,
+        stations: expected.len(),
+        threads: config.threads,
+        warmup: config.warmup,
+        runs: config.runs,
+        results: results
+            .iter()
+            .map(| (strategy, durations) | StrategyResult::new(*strategy, durations, bytes, rows))
+            .collect(),
+    })
+}
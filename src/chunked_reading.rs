@@ -1,77 +1,42 @@
-use std::collections::BTreeMap;
-use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
-use std::io::Write;
-use std::str;
 
 use ahash::RandomState;
 use anyhow::Result as Result;
 use hashbrown::HashMap;
 use rayon::prelude::*;
 
-type MeasurementsMap = HashMap<Box<[u8]>, Measurement, RandomState>;
-type SortedMeasurements = BTreeMap<Box<[u8]>, Measurement>;
+use crate::{Aggregator, Measurement, StationStats, sort_stations};
+use crate::parse::{NEWLINE, SEMICOLON, bom_length, parse_ascii_to_int, parse_unterminated};
 
-const CHUNK_SIZE: usize = 1024 * 1024;
-const NEWLINE: u8 = 10;
-const SEMICOLON: u8 = 59;
-const MINUS: u8 = 45;
-const PERIOD: u8 = 46;
-
-#[derive(Debug)]
-struct Measurement {
-    minimum: i32,
-    maximum: i32,
-    count: i32,
-    sum: i32
-}
-
-
-impl Measurement {
-    fn new(value: i32) -> Self {
-        Self { minimum: value, maximum: value, count: 1, sum: value }
-    }
+pub(crate) type MeasurementsMap = HashMap<Box<[u8]>, Measurement, RandomState>;
 
-    fn update(&mut self, value: i32) {
-        self.minimum = self.minimum.min(value);
-        self.maximum = self.maximum.max(value);
-        self.count += 1;
-        self.sum += value;
-    }
+const CHUNK_SIZE: usize = 1024 * 1024;
 
-    fn merge(&mut self, other: &Self) {
-        self.minimum = self.minimum.min(other.minimum);
-        self.maximum = self.maximum.max(other.maximum);
-        self.count += other.count;
-        self.sum += other.sum;
-    }
+/// Read one chunk per thread through a fixed size buffer instead of a memory map.
+pub struct ChunkedReading {
+    threads: usize
 }
 
-impl Default for Measurement {
-    fn default() -> Self {
-        Self { minimum: i32::MAX, maximum: i32::MIN, count: 0, sum: 0 }
+impl ChunkedReading {
+    pub fn new(threads: usize) -> Self {
+        Self { threads }
     }
 }
 
-impl Display for Measurement {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let min = self.minimum as f32 * 0.1;
-        let max = self.maximum as f32 * 0.1;
-        let avg = self.sum as f32 / self.count as f32 * 0.1;
-
-        write!(f, "{:.1}/{:.1}/{:.1}", min, avg, max)
+impl Aggregator for ChunkedReading {
+    fn aggregate(&self, file_path: &str) -> Result<StationStats> {
+        brc(file_path, self.threads)
     }
 }
 
-pub fn brc(file_path: &str) -> Result<()> {
-    let threads: usize = std::thread::available_parallelism().unwrap().into();
+pub fn brc(file_path: &str, threads: usize) -> Result<StationStats> {
     let boundaries = find_chunk_boundaries(file_path, threads)?;
     
     let parts: Vec<_> = boundaries
         .par_iter()
         .map(| (start, end) | read_chunk(file_path, *start, *end))
-        .collect();
+        .collect::<Result<_>>()?;
 
     let measurements: MeasurementsMap = parts
         .into_iter()
@@ -80,12 +45,10 @@ pub fn brc(file_path: &str) -> Result<()> {
             |mut a, b| { merge(&mut a, &b); a }
         );
     
-    let measurements: SortedMeasurements = BTreeMap::from_iter(measurements);
-    write_output(measurements)?;
-    Ok(())
+    sort_stations(measurements)
 }
 
-fn merge<'a>(
+pub(crate) fn merge(
     map_one: &mut MeasurementsMap,
     map_two: &MeasurementsMap
 ) {
@@ -98,20 +61,24 @@ fn merge<'a>(
         );
 }
 
-fn find_chunk_boundaries(file_path: &str, threads: usize) -> Result<Vec<(usize, usize)>> {
+/// Split the file into `threads` ranges, at least one, that start at the
+/// beginning of a line, leaving out a leading byte order mark.
+pub(crate) fn find_chunk_boundaries(file_path: &str, threads: usize) -> Result<Vec<(usize, usize)>> {
+    let threads = threads.max(1);
     let file: File = File::open(file_path)?;
-    let file_size: usize = file.metadata().unwrap().len() as usize;
+    let file_size: usize = file.metadata()?.len() as usize;
     let mut reader = BufReader::with_capacity(64, file);
 
     let chunk_size: usize = file_size / threads;
 
     let mut starts: Vec<usize> = Vec::with_capacity(threads);
-    starts.push(0);
+    starts.push(bom_length(reader.fill_buf()?));
     for i in 1..threads {
-        reader.seek_relative(chunk_size as i64)?;
+        let target = (i * chunk_size).max(starts[i - 1]);
+        reader.seek(SeekFrom::Start(target as u64))?;
         let mut buffer: Vec<u8> = Vec::with_capacity(64);
         let start = reader.read_until(NEWLINE, &mut buffer)?;
-        starts.push(starts[i - 1] + chunk_size + start);
+        starts.push(target + start);
     }
 
     let mut ends: Vec<usize> = vec![0; threads];
@@ -121,43 +88,58 @@ fn find_chunk_boundaries(file_path: &str, threads: usize) -> Result<Vec<(usize,
     Ok(Vec::from_iter(starts.into_iter().zip(ends)))
 }
 
-fn read_chunk(file_path: &str, start: usize, end: usize) -> MeasurementsMap {
-    let file = File::open(file_path).unwrap();
+pub(crate) fn read_chunk(file_path: &str, start: usize, end: usize) -> Result<MeasurementsMap> {
+    let file = File::open(file_path)?;
     let mut buffer: [u8; CHUNK_SIZE] = [0; CHUNK_SIZE];
 
     let mut reader = BufReader::with_capacity(CHUNK_SIZE, file);
-    reader.seek(SeekFrom::Start(start as u64)).unwrap();
+    reader.seek(SeekFrom::Start(start as u64))?;
 
     let mut measurements: MeasurementsMap = HashMap::default();
-    let mut read_total = 0;
-    let mut read_size = CHUNK_SIZE;
-
-    while (read_size == CHUNK_SIZE) && (read_total < end) {
-        read_size = reader.read(&mut buffer).unwrap();
-        let last_line_start = scan_ascii_chunk(&buffer[..read_size], &mut measurements);
-        reader.seek_relative(last_line_start as i64 - read_size as i64).unwrap();
-        read_total += last_line_start;
+    let mut position = start;
+
+    while position < end {
+        let read_size = (end - position).min(CHUNK_SIZE);
+        reader.read_exact(&mut buffer[..read_size])?;
+        let mut last_line_start = scan_ascii_chunk(&buffer[..read_size], &mut measurements);
+
+        // Without a newline this can only be the final record of the file.
+        if last_line_start == 0 {
+            if let Some((name, value)) = parse_unterminated(&buffer[..read_size]) {
+                measurements
+                    .entry_ref(name)
+                    .and_modify(| measurement | measurement.update(value))
+                    .or_insert(Measurement::new(value));
+            }
+            last_line_start = read_size;
+        }
+
+        reader.seek_relative(last_line_start as i64 - read_size as i64)?;
+        position += last_line_start;
     }
 
-    measurements
+    Ok(measurements)
 }
 
 #[inline]
 fn scan_ascii_chunk(buffer: &[u8], measurements: &mut MeasurementsMap) -> usize {
     let mut line_start = 0;
-    let mut semi_pos = 0;
+    let mut semi_pos = None;
 
     for (pos, char) in buffer.iter().enumerate() {
         match *char {
-            SEMICOLON => semi_pos = pos,
+            SEMICOLON => semi_pos = Some(pos),
             NEWLINE => {
-                let name = &buffer[(line_start)..semi_pos];
-                let value = parse_int(&buffer[(semi_pos + 1)..pos]);
+                // Lines without a separator are skipped.
+                if let Some(semi_pos) = semi_pos.take() {
+                    let name = &buffer[(line_start)..semi_pos];
+                    let value = parse_ascii_to_int(&buffer[(semi_pos + 1)..pos]);
 
-                measurements
-                    .entry_ref(name)
-                    .and_modify(| measurement | measurement.update(value))
-                    .or_insert(Measurement::new(value));
+                    measurements
+                        .entry_ref(name)
+                        .and_modify(| measurement | measurement.update(value))
+                        .or_insert(Measurement::new(value));
+                }
 
                 line_start = pos + 1;
             },
@@ -166,42 +148,3 @@ fn scan_ascii_chunk(buffer: &[u8], measurements: &mut MeasurementsMap) -> usize
     }
     line_start
 }
-
-#[inline]
-fn parse_int(buffer: &[u8]) -> i32 {
-    let mut acc: i32 = 0;
-    let mut is_neg = false;
-
-    for &val in buffer {
-        match val {
-            PERIOD => continue,
-            MINUS => is_neg = true,
-            _ => acc = acc * 10 + val as i32 - 48
-        }
-    }
-
-    match is_neg {
-        true => -acc,
-        false => acc,
-    }
-}
-
-#[inline]
-fn write_output(weather_stations: SortedMeasurements) -> Result<()>
-{
-    let mut weather_iter = weather_stations.into_iter();
-    let (first_station, first_weather) = weather_iter.next().unwrap();
-    let first_station = str::from_utf8(&first_station)?;
-
-    let stdout = std::io::stdout();
-    let mut lock = stdout.lock();
-
-    write!(lock, "{{")?;
-    write!(lock, "{first_station}={first_weather}")?;
-    for (station, weather) in  weather_iter {
-        let station = str::from_utf8(&station)?;
-        write!(lock, ", {station}={weather}")?;
-    }
-    writeln!(lock, "}}")?;
-    Ok(())
-}
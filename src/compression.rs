@@ -0,0 +1,141 @@
+//! Transparent decompression of gzip and zstd measurement files.
+//!
+//! The format is recognised by its magic bytes rather than the file name, so
+//! piped input works as well. Compressed input is fed to the
+//! [`streaming`](crate::streaming) strategy, which only needs a [`Read`].
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+
+use anyhow::{Result, anyhow};
+use flate2::bufread::MultiGzDecoder;
+use memmap2::{Mmap, MmapOptions};
+use rayon::prelude::*;
+use zstd::zstd_safe;
+
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+
+/// Compressed formats recognised by [`Compression::detect`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// The compression of a stream starting with `header`, or `None` for plain text.
+    pub fn detect(header: &[u8]) -> Option<Self> {
+        match header {
+            _ if header.starts_with(GZIP_MAGIC) => Some(Compression::Gzip),
+            _ if header.starts_with(ZSTD_MAGIC) => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// The compression of the file at `file_path`.
+    pub fn of_file(file_path: &str) -> Result<Option<Self>> {
+        let mut header = Vec::with_capacity(ZSTD_MAGIC.len());
+        File::open(file_path)?
+            .take(ZSTD_MAGIC.len() as u64)
+            .read_to_end(&mut header)?;
+        Ok(Self::detect(&header))
+    }
+}
+
+/// Decompress `reader` if it starts with the magic bytes of a known format.
+pub fn decompress<'a, R: BufRead + 'a>(mut reader: R) -> Result<Box<dyn Read + 'a>> {
+    Ok(match Compression::detect(reader.fill_buf()?) {
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(reader)),
+        Some(Compression::Zstd) => Box::new(zstd::Decoder::with_buffer(reader)?),
+        None => Box::new(reader),
+    })
+}
+
+/// Open `file_path` for reading, decompressing it when needed.
+///
+/// Zstd files made of several frames, as written by `pzstd` or `zstd -B`,
+/// decode `threads` frames at a time in parallel.
+pub fn open(file_path: &str, threads: usize) -> Result<Box<dyn Read>> {
+    let file = File::open(file_path)?;
+    if Compression::of_file(file_path)? != Some(Compression::Zstd) {
+        return decompress(BufReader::with_capacity(1 << 20, file));
+    }
+
+    let data = unsafe { MmapOptions::new().map(&file)? };
+    let frames = find_frames(&data)?;
+    match frames.len() {
+        0 | 1 => decompress(BufReader::with_capacity(1 << 20, file)),
+        _ => Ok(Box::new(ZstdFrames::new(data, frames, threads))),
+    }
+}
+
+/// Byte ranges of the consecutive zstd frames in `data`.
+fn find_frames(data: &[u8]) -> Result<Vec<(usize, usize)>> {
+    let mut frames = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let length = zstd_safe::find_frame_compressed_size(&data[start..]).map_err(
+            | code | anyhow!("invalid zstd frame at byte {start}: {}", zstd_safe::get_error_name(code))
+        )?;
+        frames.push((start, start + length));
+        start += length;
+    }
+    Ok(frames)
+}
+
+/// Reader over the decompressed contents of a multi-frame zstd file, decoding
+/// the next batch of frames in parallel once the current one is consumed.
+struct ZstdFrames {
+    data: Mmap,
+    frames: Vec<(usize, usize)>,
+    next_frame: usize,
+    threads: usize,
+    decoded: VecDeque<Vec<u8>>,
+    current: Cursor<Vec<u8>>,
+}
+
+impl ZstdFrames {
+    fn new(data: Mmap, frames: Vec<(usize, usize)>, threads: usize) -> Self {
+        Self {
+            data,
+            frames,
+            next_frame: 0,
+            threads: threads.max(1),
+            decoded: VecDeque::new(),
+            current: Cursor::default(),
+        }
+    }
+
+    /// Decode the next batch of frames, returning `false` once all are done.
+    fn decode_batch(&mut self) -> io::Result<bool> {
+        let end = (self.next_frame + self.threads).min(self.frames.len());
+        if self.next_frame == end {
+            return Ok(false);
+        }
+        let decoded: Vec<Vec<u8>> = self.frames[self.next_frame..end]
+            .par_iter()
+            .map(| &(start, end) | zstd::decode_all(&self.data[start..end]))
+            .collect::<io::Result<_>>()?;
+        self.decoded.extend(decoded);
+        self.next_frame = end;
+        Ok(true)
+    }
+}
+
+impl Read for ZstdFrames {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.current.read(buffer)?;
+            if read > 0 || buffer.is_empty() {
+                return Ok(read);
+            }
+            match self.decoded.pop_front() {
+                Some(frame) => self.current = Cursor::new(frame),
+                None if self.decode_batch()? => continue,
+                None => return Ok(0),
+            }
+        }
+    }
+}
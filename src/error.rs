@@ -0,0 +1,65 @@
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+/// Longest station name allowed by the challenge rules, in bytes.
+pub const MAX_NAME_LENGTH: usize = 100;
+
+/// What is wrong with a malformed record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordErrorKind {
+    MissingSeparator,
+    EmptyName,
+    NameTooLong(usize),
+    InvalidUtf8,
+    InvalidValue,
+    ValueOutOfRange,
+    CarriageReturn,
+    ByteOrderMark,
+}
+
+impl Display for RecordErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordErrorKind::MissingSeparator => write!(f, "missing `;` separator"),
+            RecordErrorKind::EmptyName => write!(f, "empty station name"),
+            RecordErrorKind::NameTooLong(length) => {
+                write!(f, "station name of {length} bytes exceeds {MAX_NAME_LENGTH} bytes")
+            },
+            RecordErrorKind::InvalidUtf8 => write!(f, "station name is not valid UTF-8"),
+            RecordErrorKind::InvalidValue => write!(f, "temperature is not of the form `-?d?d.d`"),
+            RecordErrorKind::ValueOutOfRange => write!(f, "temperature outside -99.9..=99.9"),
+            RecordErrorKind::CarriageReturn => write!(f, "`\\r\\n` line ending"),
+            RecordErrorKind::ByteOrderMark => write!(f, "UTF-8 byte order mark"),
+        }
+    }
+}
+
+/// A malformed record, located by the byte offset of its line start and
+/// its 1-based line number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordError {
+    pub kind: RecordErrorKind,
+    pub offset: u64,
+    pub line: u64,
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} (byte {}): {}", self.line, self.offset, self.kind)
+    }
+}
+
+impl Error for RecordError {}
+
+/// The input did not contain a single aggregated record, for example an empty
+/// file or one where every line was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoData;
+
+impl Display for NoData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "input contains no measurements")
+    }
+}
+
+impl Error for NoData {}
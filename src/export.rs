@@ -0,0 +1,78 @@
+//! Arrow IPC and Parquet export of the aggregated stations, so tools such as
+//! polars can load the results without parsing text.
+//!
+//! The table has one row per station in sorted order, with `name`, `min`,
+//! `mean`, `max` and `sum` in degrees and the `count` of measurements.
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use arrow_array::{Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::writer::FileWriter;
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::StationStats;
+
+/// Schema of the exported table.
+pub fn schema() -> Schema {
+    Schema::new(vec![
+        Field::new("name", DataType::Utf8, false),
+        Field::new("min", DataType::Float64, false),
+        Field::new("mean", DataType::Float64, false),
+        Field::new("max", DataType::Float64, false),
+        Field::new("count", DataType::UInt64, false),
+        Field::new("sum", DataType::Float64, false),
+    ])
+}
+
+/// The stations as a single record batch. The mean is the exact `sum / count`,
+/// not rounded to one decimal like in the text output.
+pub fn to_record_batch(weather_stations: &StationStats) -> Result<RecordBatch> {
+    let stations = weather_stations.as_slice();
+    let tenths = | value: i64 | value as f64 / 10.;
+    let columns: Vec<Arc<dyn arrow_array::Array>> = vec![
+        Arc::new(StringArray::from_iter_values(stations.iter().map(| station | station.name()))),
+        Arc::new(Float64Array::from_iter_values(
+            stations.iter().map(| station | tenths(station.measurement().minimum as i64))
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            stations.iter().map(| station | station.mean())
+        )),
+        Arc::new(Float64Array::from_iter_values(
+            stations.iter().map(| station | tenths(station.measurement().maximum as i64))
+        )),
+        Arc::new(UInt64Array::from_iter_values(stations.iter().map(| station | station.count()))),
+        Arc::new(Float64Array::from_iter_values(
+            stations.iter().map(| station | tenths(station.measurement().sum))
+        )),
+    ];
+    Ok(RecordBatch::try_new(Arc::new(schema()), columns)?)
+}
+
+/// Write the stations as an Arrow IPC file, also known as Feather v2.
+pub fn write_arrow(weather_stations: &StationStats, path: &Path) -> Result<()> {
+    let batch = to_record_batch(weather_stations)?;
+    let file = File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut writer = FileWriter::try_new(file, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Write the stations as a Snappy compressed Parquet file.
+pub fn write_parquet(weather_stations: &StationStats, path: &Path) -> Result<()> {
+    let batch = to_record_batch(weather_stations)?;
+    let file = File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
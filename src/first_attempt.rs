@@ -1,60 +1,38 @@
 use std::collections::HashMap;
 use std::fs;
 
-#[derive(Debug)]
-struct Measurement {
-    minimum: f32,
-    maximum: f32,
-    count: u32,
-    sum: f32
-}
+use anyhow::Result;
 
-impl Measurement {
-    fn new() -> Self {
-        Self { minimum: f32::MAX, maximum: f32::MIN, count: 0, sum: 0. }
-    }
-    
-    fn update(&mut self, value: f32) {
-        self.minimum = self.minimum.min(value);
-        self.maximum = self.maximum.max(value);
-        self.count += 1;
-        self.sum += value;
+use crate::{Aggregator, Measurement, StationStats, sort_stations};
+use crate::parse::parse_ascii_to_int;
+
+/// Read the whole file as a string and aggregate it line by line.
+pub struct FirstAttempt;
+
+impl Aggregator for FirstAttempt {
+    fn aggregate(&self, file_path: &str) -> Result<StationStats> {
+        brc(file_path)
     }
 }
 
 
-pub fn brc(file_path: &str) {
-    let data: String = fs::read_to_string(file_path).expect("Could not read the file");
+pub fn brc(file_path: &str) -> Result<StationStats> {
+    let data: String = fs::read_to_string(file_path)?;
     
     let mut weather_stations: HashMap<&str, Measurement> = HashMap::new();
     
-    for line in data.lines() {
+    for line in data.strip_prefix('\u{feff}').unwrap_or(&data).lines() {
         let (location, temperature) = match line.split_once(';') {
             Some((loc, val)) => (loc, val),
             None => continue
         };
         
-        let measurement_value: f32 = temperature.parse().unwrap();
+        let measurement_value = parse_ascii_to_int(temperature.as_bytes());
         weather_stations
             .entry(location)
-            .or_insert(Measurement::new())
+            .or_default()
             .update(measurement_value);
     }
     
-    let mut weather_stations: Vec<(&str, Measurement)> = weather_stations.into_iter().collect();
-    weather_stations.sort_by_key(| item | item.0);
-    
-    let mut weather_iter = weather_stations.into_iter();
-    
-    let (first_station, first_weather) = weather_iter.next().unwrap();
-    let avg = first_weather.sum / (first_weather.count as f32);
-    
-    print!("{{");
-    print!("{first_station}={:.1}/{:.1}/{:.1}", first_weather.minimum, avg, first_weather.maximum);
-    
-    for (station, weather) in  weather_iter {
-        let avg = weather.sum / (weather.count as f32);
-        print!(", {station}={:.1}/{:.1}/{:.1}", weather.minimum, avg, weather.maximum);
-    }
-    println!("}}");
+    sort_stations(weather_stations)
 }
@@ -0,0 +1,410 @@
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use clap::ValueEnum;
+
+use crate::{Station, StationStats};
+use crate::multi_file::FileStats;
+
+/// Output formats selectable on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// `{Name=min/mean/max, ...}` as in the challenge.
+    #[default]
+    Text,
+    /// One object keyed by station name.
+    Json,
+    /// One object per station and line, written as the stations are visited.
+    Jsonl,
+    /// Comma separated values with a header row.
+    Csv,
+    /// Tab separated values with a header row.
+    Tsv,
+}
+
+impl OutputFormat {
+    fn separator(self) -> Option<u8> {
+        match self {
+            OutputFormat::Csv => Some(b','),
+            OutputFormat::Tsv => Some(b'\t'),
+            _ => None,
+        }
+    }
+}
+
+/// Statistics printed after the default ones when asked for, parsed from
+/// `variance`, `stddev`, `median` or `p` followed by a percentile such as `p99.9`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Column {
+    /// Population variance, in square degrees.
+    Variance,
+    /// Population standard deviation, in degrees.
+    Stddev,
+    /// Nearest-rank percentile between 0 and 100, in degrees. Only stations
+    /// aggregated with histograms have one, the others print an empty value.
+    Percentile(f64),
+}
+
+impl Column {
+    /// Whether the column needs the stations' histograms.
+    pub fn needs_histogram(self) -> bool {
+        matches!(self, Column::Percentile(_))
+    }
+
+    fn name(self) -> String {
+        match self {
+            Column::Variance => "variance".to_owned(),
+            Column::Stddev => "stddev".to_owned(),
+            Column::Percentile(50.) => "median".to_owned(),
+            Column::Percentile(percentile) => format!("p{percentile}"),
+        }
+    }
+
+    fn value(self, station: &Station) -> Option<f64> {
+        match self {
+            Column::Variance => Some(station.variance()),
+            Column::Stddev => Some(station.std_dev()),
+            Column::Percentile(percentile) => station.percentile(percentile),
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(column: &str) -> Result<Self, Self::Err> {
+        let percentile = match column {
+            "variance" => return Ok(Column::Variance),
+            "stddev" => return Ok(Column::Stddev),
+            "median" => "50",
+            _ => column.strip_prefix('p').ok_or_else(
+                || format!("unknown column `{column}`, expected variance, stddev, median or pN")
+            )?,
+        };
+        match percentile.parse::<f64>() {
+            Ok(percentile) if (0. ..=100.).contains(&percentile) => Ok(Column::Percentile(percentile)),
+            _ => Err(format!("`{column}` is not a percentile between p0 and p100")),
+        }
+    }
+}
+
+/// Largest number of decimals supported by [`write_delimited`].
+pub const MAX_PRECISION: usize = 9;
+
+/// A value in tenths of a degree, displayed with a single decimal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tenths(pub i64);
+
+impl Display for Tenths {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let value = self.0.unsigned_abs();
+        write!(f, "{sign}{}.{}", value / 10, value % 10)
+    }
+}
+
+/// Write the stations as `{Name=min/mean/max, ...}`, the challenge's output format.
+pub fn write_output<W: Write>(weather_stations: &StationStats, writer: W) -> io::Result<()> {
+    write_text(weather_stations, &[], writer)
+}
+
+/// Write the stations as `{Name=min/mean/max/extra..., ...}`, with the extra
+/// `columns` at full precision and `-` for missing percentiles.
+pub fn write_text<W: Write>(
+    weather_stations: &StationStats,
+    columns: &[Column],
+    mut writer: W,
+) -> io::Result<()> {
+    write!(writer, "{{")?;
+    for (index, station) in weather_stations.iter().enumerate() {
+        if index > 0 {
+            write!(writer, ", ")?;
+        }
+        write_station(&mut writer, station)?;
+        for column in columns {
+            write!(writer, "/")?;
+            match column.value(station) {
+                Some(value) => write_number(&mut writer, value)?,
+                None => write!(writer, "-")?,
+            }
+        }
+    }
+    writeln!(writer, "}}")?;
+    writer.flush()
+}
+
+/// Write the stations in `format` followed by the extra `columns`, with
+/// `precision` decimals in CSV and TSV.
+pub fn write_stats<W: Write>(
+    weather_stations: &StationStats,
+    format: OutputFormat,
+    precision: usize,
+    columns: &[Column],
+    mut writer: W,
+) -> io::Result<()> {
+    match (format, format.separator()) {
+        (_, Some(separator)) => {
+            write_header(&mut writer, separator, columns, false)?;
+            write_delimited(weather_stations, separator, precision, columns, None, writer)
+        },
+        (OutputFormat::Json, _) => write_json(weather_stations, columns, writer),
+        (OutputFormat::Jsonl, _) => write_json_lines(weather_stations, columns, None, writer),
+        _ => write_text(weather_stations, columns, writer),
+    }
+}
+
+/// Write every file's stations followed by the combined ones. JSON nests them
+/// as `{"files": {path: stations}, "total": stations}`, JSON Lines adds a
+/// `file` key to the lines of each file and CSV and TSV a `file` column that
+/// is empty for the combined rows.
+pub fn write_files<W: Write>(
+    files: &[FileStats],
+    total: &StationStats,
+    format: OutputFormat,
+    precision: usize,
+    columns: &[Column],
+    mut writer: W,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Csv => write_delimited_files(files, total, b',', precision, columns, writer),
+        OutputFormat::Tsv => write_delimited_files(files, total, b'\t', precision, columns, writer),
+        OutputFormat::Text => {
+            for file in files {
+                write!(writer, "{}: ", file.path.display())?;
+                write_text(&file.stats, columns, &mut writer)?;
+            }
+            write_text(total, columns, writer)
+        },
+        OutputFormat::Json => {
+            write!(writer, "{{\"files\":{{")?;
+            for (index, file) in files.iter().enumerate() {
+                if index > 0 {
+                    write!(writer, ",")?;
+                }
+                write_json_string(&mut writer, &file.path.display().to_string())?;
+                write!(writer, ":")?;
+                write_json_object(&mut writer, &file.stats, columns)?;
+            }
+            write!(writer, "}},\"total\":")?;
+            write_json_object(&mut writer, total, columns)?;
+            writeln!(writer, "}}")?;
+            writer.flush()
+        },
+        OutputFormat::Jsonl => {
+            for file in files {
+                let path = file.path.display().to_string();
+                write_json_lines(&file.stats, columns, Some(&path), &mut writer)?;
+            }
+            write_json_lines(total, columns, None, writer)
+        },
+    }
+}
+
+fn write_delimited_files<W: Write>(
+    files: &[FileStats],
+    total: &StationStats,
+    separator: u8,
+    precision: usize,
+    columns: &[Column],
+    mut writer: W,
+) -> io::Result<()> {
+    write_header(&mut writer, separator, columns, true)?;
+    for file in files {
+        let path = file.path.display().to_string();
+        write_delimited(&file.stats, separator, precision, columns, Some(&path), &mut writer)?;
+    }
+    write_delimited(total, separator, precision, columns, Some(""), writer)
+}
+
+/// Write the stations as a JSON object keyed by name, with `min`, `mean`, `max`,
+/// `count`, `sum` and the extra `columns` in degrees.
+pub fn write_json<W: Write>(
+    weather_stations: &StationStats,
+    columns: &[Column],
+    mut writer: W,
+) -> io::Result<()> {
+    write_json_object(&mut writer, weather_stations, columns)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// Write one JSON object per station and line, with its name under `station`
+/// and `file` when given.
+pub fn write_json_lines<W: Write>(
+    weather_stations: &StationStats,
+    columns: &[Column],
+    file: Option<&str>,
+    mut writer: W,
+) -> io::Result<()> {
+    for station in weather_stations {
+        write!(writer, "{{")?;
+        if let Some(file) = file {
+            write!(writer, "\"file\":")?;
+            write_json_string(&mut writer, file)?;
+            write!(writer, ",")?;
+        }
+        write!(writer, "\"station\":")?;
+        write_json_string(&mut writer, station.name())?;
+        write!(writer, ",")?;
+        write_json_fields(&mut writer, station, columns)?;
+        writeln!(writer, "}}")?;
+    }
+    writer.flush()
+}
+
+/// Write one row per station separated by `separator`, with names quoted as
+/// in RFC 4180 when they contain the separator, a quote or a line break.
+/// The default values are rounded half up to `precision` decimals, the extra
+/// `columns` are rounded to nearest by float formatting. The header is left to
+/// the caller.
+pub fn write_delimited<W: Write>(
+    weather_stations: &StationStats,
+    separator: u8,
+    precision: usize,
+    columns: &[Column],
+    file: Option<&str>,
+    mut writer: W,
+) -> io::Result<()> {
+    let separator = separator as char;
+    for station in weather_stations {
+        let measurement = station.measurement();
+        if let Some(file) = file {
+            write_field(&mut writer, file, separator)?;
+            write!(writer, "{separator}")?;
+        }
+        write_field(&mut writer, station.name(), separator)?;
+        write!(
+            writer,
+            "{separator}{}{separator}{}{separator}{}{separator}{}{separator}{}",
+            Decimal::new(measurement.minimum as i64, 1, precision),
+            Decimal::new(measurement.sum, measurement.count, precision),
+            Decimal::new(measurement.maximum as i64, 1, precision),
+            measurement.count,
+            Decimal::new(measurement.sum, 1, precision),
+        )?;
+        for column in columns {
+            write!(writer, "{separator}")?;
+            if let Some(value) = column.value(station) {
+                write!(writer, "{value:.precision$}")?;
+            }
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()
+}
+
+fn write_header<W: Write>(
+    writer: &mut W,
+    separator: u8,
+    columns: &[Column],
+    with_file: bool,
+) -> io::Result<()> {
+    let separator = separator as char;
+    if with_file {
+        write!(writer, "file{separator}")?;
+    }
+    write!(writer, "station{separator}min{separator}mean{separator}max{separator}count{separator}sum")?;
+    for column in columns {
+        write!(writer, "{separator}{}", column.name())?;
+    }
+    writeln!(writer)
+}
+
+fn write_field<W: Write>(writer: &mut W, value: &str, separator: char) -> io::Result<()> {
+    match value.contains([separator, '"', '\n', '\r']) {
+        true => write!(writer, "\"{}\"", value.replace('"', "\"\"")),
+        false => write!(writer, "{value}"),
+    }
+}
+
+/// `tenths / count` degrees rounded half up to `precision` decimals.
+struct Decimal {
+    scaled: i128,
+    precision: usize,
+}
+
+impl Decimal {
+    fn new(tenths: i64, count: u64, precision: usize) -> Self {
+        let precision = precision.min(MAX_PRECISION);
+        let denominator = 10 * count.max(1) as i128;
+        let numerator = tenths as i128 * 10i128.pow(precision as u32);
+        let scaled = (2 * numerator + denominator).div_euclid(2 * denominator);
+        Self { scaled, precision }
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let sign = if self.scaled < 0 { "-" } else { "" };
+        let value = self.scaled.unsigned_abs();
+        let unit = 10u128.pow(self.precision as u32);
+        match self.precision {
+            0 => write!(f, "{sign}{value}"),
+            precision => write!(f, "{sign}{}.{:0precision$}", value / unit, value % unit),
+        }
+    }
+}
+
+fn write_json_object<W: Write>(
+    writer: &mut W,
+    weather_stations: &StationStats,
+    columns: &[Column],
+) -> io::Result<()> {
+    write!(writer, "{{")?;
+    for (index, station) in weather_stations.iter().enumerate() {
+        if index > 0 {
+            write!(writer, ",")?;
+        }
+        write_json_string(writer, station.name())?;
+        write!(writer, ":{{")?;
+        write_json_fields(writer, station, columns)?;
+        write!(writer, "}}")?;
+    }
+    write!(writer, "}}")
+}
+
+fn write_json_fields<W: Write>(
+    writer: &mut W,
+    station: &Station,
+    columns: &[Column],
+) -> io::Result<()> {
+    let measurement = station.measurement();
+    let min = Tenths(measurement.minimum as i64);
+    let mean = Tenths(measurement.rounded_mean());
+    let max = Tenths(measurement.maximum as i64);
+    let sum = Tenths(measurement.sum);
+
+    write!(
+        writer,
+        "\"min\":{min},\"mean\":{mean},\"max\":{max},\"count\":{},\"sum\":{sum}",
+        measurement.count,
+    )?;
+    for column in columns {
+        write!(writer, ",\"{}\":", column.name())?;
+        match column.value(station) {
+            Some(value) => write_number(writer, value)?,
+            None => write!(writer, "null")?,
+        }
+    }
+    Ok(())
+}
+
+/// Write `value` as a JSON string, escaping quotes, backslashes and control characters.
+fn write_json_string<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    serde_json::to_writer(writer, value).map_err(io::Error::from)
+}
+
+/// Write `value` in the shortest form that reads back as the same `f64`.
+fn write_number<W: Write>(writer: &mut W, value: f64) -> io::Result<()> {
+    serde_json::to_writer(writer, &value).map_err(io::Error::from)
+}
+
+fn write_station<W: Write>(writer: &mut W, station: &Station) -> io::Result<()> {
+    let measurement = station.measurement();
+    let min = Tenths(measurement.minimum as i64);
+    let mean = Tenths(measurement.rounded_mean());
+    let max = Tenths(measurement.maximum as i64);
+
+    write!(writer, "{}={min}/{mean}/{max}", station.name())
+}
@@ -0,0 +1,189 @@
+//! Generator for measurement files, a built-in replacement for the 1BRC
+//! `create_measurements` tool.
+//!
+//! The output only depends on the row count, the seed and the station set,
+//! not on the number of threads used to produce it.
+
+use std::collections::HashSet;
+use std::io::Write;
+
+use anyhow::{Result, bail};
+use rayon::prelude::*;
+
+use crate::error::MAX_NAME_LENGTH;
+use crate::parse::{MINUS, NEWLINE, PERIOD, SEMICOLON};
+use crate::stations::STATIONS;
+
+/// Upper bound of unique station names allowed by the challenge rules.
+pub const MAX_UNIQUE_STATIONS: usize = 10_000;
+
+/// Rows rendered per block, every block has its own random stream.
+const BLOCK_ROWS: u64 = 64 * 1024;
+
+/// Standard deviation of the generated temperatures around the station mean.
+const STANDARD_DEVIATION: f64 = 10.;
+
+/// Characters of generated station names, including multi-byte ones.
+const NAME_CHARACTERS: &[char] = &[
+    'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o', 'p', 'q', 'r',
+    's', 't', 'u', 'v', 'w', 'x', 'y', 'z', 'A', 'B', 'K', 'M', 'S', 'Z', ' ', '-', '\'', '.',
+    'é', 'ü', 'ø', 'ł', 'ş', 'İ', 'ō', 'ñ', '北', '京', 'ד', 'ж',
+];
+
+/// Writes `rows` random measurements in the `station;temperature` format.
+pub struct Generator {
+    rows: u64,
+    seed: u64,
+    unique_stations: Option<usize>,
+}
+
+impl Generator {
+    pub fn new(rows: u64, seed: u64) -> Self {
+        Self { rows, seed, unique_stations: None }
+    }
+
+    /// Draw from `count` generated station names instead of the built-in table,
+    /// like the 10k unique keys variant of the challenge.
+    pub fn unique_stations(mut self, count: usize) -> Self {
+        self.unique_stations = Some(count);
+        self
+    }
+
+    /// Render blocks of rows in parallel on the rayon pool and write them in order.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<()> {
+        let stations = self.stations()?;
+        let blocks = self.rows.div_ceil(BLOCK_ROWS);
+        let batch = 4 * rayon::current_num_threads() as u64;
+
+        for batch_start in (0..blocks).step_by(batch as usize) {
+            let buffers: Vec<Vec<u8>> = (batch_start..(batch_start + batch).min(blocks))
+                .into_par_iter()
+                .map(| block | self.render_block(block, &stations))
+                .collect();
+            for buffer in buffers {
+                writer.write_all(&buffer)?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn stations(&self) -> Result<Vec<(String, f64)>> {
+        let count = match self.unique_stations {
+            None => {
+                return Ok(STATIONS.iter().map(| &(name, mean) | (name.to_owned(), mean)).collect())
+            },
+            Some(count) => count,
+        };
+        if count == 0 || count > MAX_UNIQUE_STATIONS {
+            bail!("unique stations must be between 1 and {MAX_UNIQUE_STATIONS}, got {count}");
+        }
+
+        let mut rng = SplitMix64::new(mix(self.seed ^ 0x5EED_57A7_1045));
+        let mut names = HashSet::with_capacity(count);
+        let mut stations = Vec::with_capacity(count);
+        while stations.len() < count {
+            let name = random_name(&mut rng);
+            if names.insert(name.clone()) {
+                let mean = rng.next_f64() * 70. - 30.;
+                stations.push((name, mean));
+            }
+        }
+        Ok(stations)
+    }
+
+    fn render_block(&self, block: u64, stations: &[(String, f64)]) -> Vec<u8> {
+        // Adding a multiple of the generator's own increment would give every
+        // block the previous block's stream shifted by one draw, so the block
+        // number is mixed in instead.
+        let mut rng = SplitMix64::new(mix(self.seed ^ mix(block.wrapping_add(1))));
+        let start = block * BLOCK_ROWS;
+        let rows = (self.rows - start).min(BLOCK_ROWS);
+
+        let mut buffer = Vec::with_capacity(rows as usize * 16);
+        for _ in 0..rows {
+            let (name, mean) = &stations[rng.below(stations.len() as u64) as usize];
+            let value = ((mean + STANDARD_DEVIATION * rng.gaussian()) * 10.).round() as i32;
+
+            buffer.extend_from_slice(name.as_bytes());
+            buffer.push(SEMICOLON);
+            push_tenths(&mut buffer, value.clamp(-999, 999));
+            buffer.push(NEWLINE);
+        }
+        buffer
+    }
+}
+
+/// A station name of 1 to 40 characters that fits in [`MAX_NAME_LENGTH`] bytes.
+fn random_name(rng: &mut SplitMix64) -> String {
+    let characters = 1 + rng.below(40) as usize;
+    let mut name = String::with_capacity(characters * 2);
+    for _ in 0..characters {
+        let character = NAME_CHARACTERS[rng.below(NAME_CHARACTERS.len() as u64) as usize];
+        if name.len() + character.len_utf8() > MAX_NAME_LENGTH {
+            break;
+        }
+        name.push(character);
+    }
+    // Surrounding spaces are easily lost by downstream tools.
+    match name.trim() {
+        "" => String::from("x"),
+        trimmed => trimmed.to_owned(),
+    }
+}
+
+fn push_tenths(buffer: &mut Vec<u8>, value: i32) {
+    if value < 0 {
+        buffer.push(MINUS);
+    }
+    let value = value.unsigned_abs();
+    if value >= 100 {
+        buffer.push(b'0' + (value / 100) as u8);
+    }
+    buffer.push(b'0' + (value / 10 % 10) as u8);
+    buffer.push(PERIOD);
+    buffer.push(b'0' + (value % 10) as u8);
+}
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// SplitMix64's output function, a bijection that scrambles every input bit.
+fn mix(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Small, fast and stable pseudo random generator, so a seed always produces
+/// the same file regardless of dependency versions.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn new(seed: u64) -> Self {
+        let mut rng = Self(seed);
+        rng.next_u64();
+        rng
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(GOLDEN_GAMMA);
+        mix(self.0)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in `0..bound`.
+    fn below(&mut self, bound: u64) -> u64 {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as u64
+    }
+
+    /// Standard normal sample using the Box-Muller transform.
+    fn gaussian(&mut self) -> f64 {
+        let radius = (-2. * (1. - self.next_f64()).ln()).sqrt();
+        let angle = std::f64::consts::TAU * self.next_f64();
+        radius * angle.cos()
+    }
+}
@@ -0,0 +1,140 @@
+/// Lowest value a bin is kept for, in tenths of a degree.
+pub const MIN_VALUE: i32 = -999;
+
+/// Highest value a bin is kept for, in tenths of a degree.
+pub const MAX_VALUE: i32 = 999;
+
+const BINS: usize = (MAX_VALUE - MIN_VALUE + 1) as usize;
+
+/// Number of raw values that take as much memory as the dense bins, where a
+/// histogram switches from one to the other.
+const DENSE_THRESHOLD: usize = BINS * size_of::<u64>() / size_of::<i16>();
+
+/// Exact count of every value between [`MIN_VALUE`] and [`MAX_VALUE`] tenths.
+///
+/// Values outside the range are counted as the nearest one. A histogram keeps
+/// the raw values while they take less memory than one bin per value, so the
+/// many small histograms of a file with thousands of stations split over many
+/// threads cost about two bytes per row instead of 16 KiB each.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    storage: Storage
+}
+
+#[derive(Debug, Clone)]
+enum Storage {
+    Values(Vec<i16>),
+    Bins(Box<[u64; BINS]>),
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self { storage: Storage::Values(Vec::new()) }
+    }
+
+    #[inline]
+    pub fn add(&mut self, value: i32) {
+        let value = value.clamp(MIN_VALUE, MAX_VALUE);
+        match &mut self.storage {
+            Storage::Values(values) if values.len() < DENSE_THRESHOLD => values.push(value as i16),
+            Storage::Values(_) => {
+                self.make_dense();
+                self.add(value);
+            },
+            Storage::Bins(bins) => bins[(value - MIN_VALUE) as usize] += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        match (&mut self.storage, &other.storage) {
+            (Storage::Values(values), Storage::Values(others))
+                if values.len() + others.len() <= DENSE_THRESHOLD => values.extend(others),
+            (Storage::Bins(bins), Storage::Bins(others)) => {
+                for (bin, count) in bins.iter_mut().zip(others.iter()) {
+                    *bin += count;
+                }
+            },
+            (Storage::Bins(bins), Storage::Values(others)) => {
+                for &value in others {
+                    bins[(value as i32 - MIN_VALUE) as usize] += 1;
+                }
+            },
+            _ => {
+                self.make_dense();
+                self.merge(other);
+            },
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        match &self.storage {
+            Storage::Values(values) => values.len() as u64,
+            Storage::Bins(bins) => bins.iter().sum(),
+        }
+    }
+
+    /// The nearest-rank `percentile`, between 0 and 100, in tenths of a degree:
+    /// the smallest value that at least `percentile` percent of the values are
+    /// less than or equal to. `None` when the histogram is empty.
+    pub fn percentile(&self, percentile: f64) -> Option<i32> {
+        let rank = rank(percentile, self.count())?;
+        let mut seen = 0;
+        for (index, bin) in self.bins().iter().enumerate() {
+            seen += bin;
+            if seen >= rank {
+                return Some(index as i32 + MIN_VALUE);
+            }
+        }
+        None
+    }
+
+    /// The count of every value, from [`MIN_VALUE`] up.
+    pub fn bins(&self) -> Box<[u64; BINS]> {
+        match &self.storage {
+            Storage::Values(values) => {
+                let mut bins = Box::new([0; BINS]);
+                for &value in values {
+                    bins[(value as i32 - MIN_VALUE) as usize] += 1;
+                }
+                bins
+            },
+            Storage::Bins(bins) => bins.clone(),
+        }
+    }
+
+    fn make_dense(&mut self) {
+        if let Storage::Values(_) = self.storage {
+            self.storage = Storage::Bins(self.bins());
+        }
+    }
+}
+
+/// The 1-based rank of the nearest-rank `percentile` of `count` values,
+/// `ceil(percentile / 100 * count)` but at least 1.
+///
+/// The percentile is taken to six decimals and the rest is integer arithmetic,
+/// so `p7` of 100 values is rank 7 rather than the 8 that `7. / 100. * 100.`
+/// rounds up to in floating point.
+fn rank(percentile: f64, count: u64) -> Option<u64> {
+    const SCALE: u128 = 100_000_000;
+    let millionths = (percentile.clamp(0., 100.) * 1e6).round() as u128;
+    match count {
+        0 => None,
+        count => Some((millionths * count as u128).div_ceil(SCALE).max(1) as u64),
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Histograms are equal when they count the same values, however they store them.
+impl PartialEq for Histogram {
+    fn eq(&self, other: &Self) -> bool {
+        self.bins() == other.bins()
+    }
+}
+
+impl Eq for Histogram {}
@@ -0,0 +1,48 @@
+//! Implementations of the one billion row challenge.
+//!
+//! Every strategy implements [`Aggregator`] and produces the same sorted
+//! [`StationStats`], so they can be swapped freely by callers. Rendering
+//! the results is left to the [`format`] module.
+
+use anyhow::Result;
+
+pub mod bench;
+pub mod compression;
+pub mod error;
+#[cfg(feature = "arrow")]
+pub mod export;
+pub mod format;
+pub mod generate;
+pub mod histogram;
+mod measurement;
+pub mod multi_file;
+pub mod parse;
+mod stations;
+mod stats;
+mod strategy;
+
+pub mod first_attempt;
+pub mod first_attempt_alternative;
+pub mod first_attempt_vec;
+pub mod improved_file_read;
+pub mod multithreaded_rayon;
+pub mod multithreaded_manual;
+pub mod prototyping;
+pub mod chunked_reading;
+pub mod multithreaded_single_map;
+pub mod open_addressing;
+pub mod segmented;
+pub mod simd_scan;
+pub mod streaming;
+pub mod validating;
+
+pub use measurement::Measurement;
+pub use stats::{Station, StationStats};
+pub use strategy::Strategy;
+
+pub(crate) use stats::{Accumulator, sort_stations};
+
+/// A way of aggregating a measurements file into per station statistics.
+pub trait Aggregator {
+    fn aggregate(&self, file_path: &str) -> Result<StationStats>;
+}
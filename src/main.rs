@@ -1,30 +1,365 @@
-
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::time::Instant;
 
-mod first_attempt;
-mod first_attempt_alternative;
-mod first_attempt_vec;
-mod improved_file_read;
-mod multithreaded_rayon;
-mod multithreaded_manual;
-mod prototyping;
-mod chunked_reading;
-mod multithreaded_single_map;
-
-
-fn main() {
-    // let input_file = "C:/Users/Max/Downloads/1brc-main/data/measurements_small.txt";
-    let input_file = "C:/Users/Max/Downloads/1brc-main/data/measurements.txt";
-    
+use anyhow::{Context, Result};
+use brc::{Aggregator, StationStats, Strategy};
+use brc::bench::{self, BenchConfig, BenchReport};
+use brc::compression::Compression;
+use brc::format::{Column, OutputFormat, write_files, write_stats};
+use brc::generate::Generator;
+use brc::multi_file::{aggregate_files, expand_inputs};
+use brc::multithreaded_rayon::MultithreadedRayon;
+use brc::segmented::Segmented;
+use brc::streaming::{self, Streaming};
+use brc::validating::{Validating, Validation};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+
+/// Aggregate min/mean/max temperatures per weather station.
+#[derive(Parser)]
+#[command(version, args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Measurements files with one `station;temperature` record per line, `-`
+    /// streams from stdin. Directories and glob patterns expand to the files they
+    /// match. Gzip and zstd input is decompressed on the fly.
+    #[arg(required = true)]
+    input: Vec<String>,
+
+    /// Implementation used to process a single file, `multithreaded_rayon` by
+    /// default. Ignored with `--strict` or `--lenient`. Stdin and compressed
+    /// input are always streamed and only accept `streaming`.
+    #[arg(short, long, value_enum)]
+    strategy: Option<Strategy>,
+
+    /// Segment length for the `segmented` strategy, in bytes or with a K, M or G suffix.
+    #[arg(long, value_parser = parse_size, default_value = "8M")]
+    segment_size: usize,
+
+    /// Number of worker threads, defaults to the available parallelism.
+    #[arg(short, long, global = true)]
+    threads: Option<usize>,
+
+    /// Fail with the line number and byte offset of the first malformed record.
+    #[arg(long, conflicts_with = "lenient")]
+    strict: bool,
+
+    /// With `--strict`, reject `\r\n` line endings and a UTF-8 byte order mark
+    /// instead of stripping them.
+    #[arg(long, requires = "strict", conflicts_with = "lenient")]
+    reject_crlf: bool,
+
+    /// Skip malformed records and report how many were skipped.
+    #[arg(long)]
+    lenient: bool,
+
+    /// Output format of the statistics.
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Decimals of the values in `csv` and `tsv` output.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(0..=9))]
+    precision: u8,
+
+    /// Extra statistics printed after the default ones, comma separated:
+    /// `variance`, `stddev`, `median` or a percentile such as `p95` or `p99.9`.
+    /// Percentiles count every station's values in a histogram, which only the
+    /// `multithreaded_rayon` and `streaming` strategies do.
+    #[arg(long, value_delimiter = ',', value_name = "COLUMN")]
+    columns: Vec<Column>,
+
+    /// Also write the stations to this Arrow IPC (Feather) file.
+    #[cfg(feature = "arrow")]
+    #[arg(long, value_name = "PATH")]
+    arrow: Option<PathBuf>,
+
+    /// Also write the stations to this Parquet file.
+    #[cfg(feature = "arrow")]
+    #[arg(long, value_name = "PATH")]
+    parquet: Option<PathBuf>,
+
+    /// With several input files, also print the statistics of every file
+    /// before the combined ones.
+    #[arg(long)]
+    per_file: bool,
+
+    /// Fail instead of printing `{}` when the input has no measurements.
+    #[arg(long)]
+    require_data: bool,
+
+    /// Print the elapsed time to stderr.
+    #[arg(long, global = true)]
+    timing: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Write a measurements file of random `station;temperature` records.
+    Generate(GenerateArgs),
+
+    /// Time strategies on a measurements file and report wall time and throughput.
+    Bench(BenchArgs),
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    /// Number of records to write.
+    rows: u64,
+
+    /// Output file, `-` writes to stdout.
+    #[arg(short, long, default_value = "measurements.txt")]
+    output: String,
+
+    /// Seed of the random generator, the same seed always produces the same file.
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    /// Use this many generated station names, up to 10000, instead of the
+    /// built-in table of weather stations.
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..=10_000))]
+    unique_stations: Option<u16>,
+}
+
+#[derive(Args)]
+struct BenchArgs {
+    /// Measurements file to benchmark.
+    input: String,
+
+    /// Strategies to run, comma separated, defaults to all of them.
+    #[arg(short, long, value_enum, value_delimiter = ',')]
+    strategies: Vec<Strategy>,
+
+    /// Untimed runs of each strategy before measuring.
+    #[arg(long, default_value_t = 1)]
+    warmup: usize,
+
+    /// Timed runs of each strategy.
+    #[arg(long, default_value_t = 5)]
+    runs: usize,
+
+    /// Write the JSON report to this file, `-` writes to stdout.
+    #[arg(long)]
+    json: Option<String>,
+
+    /// Free-form label stored in the JSON report, e.g. a commit hash.
+    #[arg(long)]
+    label: Option<String>,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let threads = match cli.threads {
+        Some(0) => anyhow::bail!("--threads must be at least 1"),
+        Some(threads) => threads,
+        None => std::thread::available_parallelism()?.into(),
+    };
+    rayon::ThreadPoolBuilder::new().num_threads(threads).build_global()?;
+
     let timer = Instant::now();
-    // first_attempt::brc(input_file);
-    // first_attempt_alternative::brc(input_file);
-    // first_attemp_vec::brc(input_file).unwrap();
-    // improved_file_read::brc(input_file);
-    // multithreaded_manual::brc(input_file).unwrap();
-    multithreaded_rayon::brc(input_file).unwrap();
-    // chunked_reading::brc(input_file).unwrap();
-    // multithreaded_single_map::brc(input_file).unwrap();
-    // prototyping::brc(input_file).unwrap();
-    println!("\n{:?}", timer.elapsed());
+    match &cli.command {
+        Some(Command::Generate(args)) => generate(args)?,
+        Some(Command::Bench(args)) => benchmark(args, threads)?,
+        None => aggregate(&cli, threads)?,
+    }
+
+    if cli.timing {
+        eprintln!("{:?}", timer.elapsed());
+    }
+    Ok(())
+}
+
+fn generate(args: &GenerateArgs) -> Result<()> {
+    let generator = match args.unique_stations {
+        Some(count) => Generator::new(args.rows, args.seed).unique_stations(count.into()),
+        None => Generator::new(args.rows, args.seed),
+    };
+    let writer: Box<dyn Write> = match args.output.as_str() {
+        "-" => Box::new(std::io::stdout().lock()),
+        path => Box::new(
+            File::create(path).with_context(|| format!("failed to create {path}"))?
+        ),
+    };
+    generator
+        .write(BufWriter::with_capacity(1 << 20, writer))
+        .with_context(|| format!("failed to write {}", args.output))
+}
+
+fn benchmark(args: &BenchArgs, threads: usize) -> Result<()> {
+    let strategies = match args.strategies.is_empty() {
+        true => Strategy::value_variants().to_vec(),
+        false => args.strategies.clone(),
+    };
+    let config = BenchConfig { warmup: args.warmup, runs: args.runs, threads };
+    let report = bench::run(&args.input, &strategies, config, args.label.clone())?;
+
+    match args.json.as_deref() {
+        Some("-") => {
+            print_summary(&report, std::io::stderr().lock())?;
+            serde_json::to_writer_pretty(std::io::stdout().lock(), &report)?;
+            println!();
+        },
+        Some(path) => {
+            print_summary(&report, std::io::stdout().lock())?;
+            let file = File::create(path).with_context(|| format!("failed to create {path}"))?;
+            serde_json::to_writer_pretty(BufWriter::new(file), &report)?;
+        },
+        None => print_summary(&report, std::io::stdout().lock())?,
+    }
+    Ok(())
+}
+
+fn print_summary<W: Write>(report: &BenchReport, mut writer: W) -> Result<()> {
+    writeln!(
+        writer,
+        "{} bytes, {} rows, {} stations, {} threads, {} runs after {} warmup",
+        report.bytes, report.rows, report.stations, report.threads, report.runs, report.warmup,
+    )?;
+    writeln!(
+        writer,
+        "{:<26} {:>9} {:>9} {:>9} {:>9} {:>9} {:>12}",
+        "strategy", "min s", "median s", "stddev s", "max s", "MB/s", "rows/s",
+    )?;
+    for result in &report.results {
+        writeln!(
+            writer,
+            "{:<26} {:>9.3} {:>9.3} {:>9.3} {:>9.3} {:>9.1} {:>12.0}",
+            result.strategy, result.min, result.median, result.stddev, result.max,
+            result.mb_per_second, result.rows_per_second,
+        )?;
+    }
+    Ok(())
+}
+
+fn aggregate(cli: &Cli, threads: usize) -> Result<()> {
+    let inputs = expand_inputs(&cli.input)?;
+    let input_file = match inputs.as_slice() {
+        [input] if !cli.per_file => input.to_str().context("input path is not valid UTF-8")?,
+        _ => return aggregate_many(cli, &inputs, threads),
+    };
+    let validation = match (cli.strict, cli.lenient) {
+        (true, _) => Some(Validation::Strict),
+        (_, true) => Some(Validation::Lenient),
+        _ => None,
+    };
+    let streamed = input_file == streaming::STDIN || Compression::of_file(input_file)
+        .with_context(|| format!("failed to process {input_file}"))?
+        .is_some();
+    let histograms = cli.columns.iter().any(| column | column.needs_histogram());
+    let aggregator: Box<dyn Aggregator> = match validation {
+        Some(_) if streamed => {
+            anyhow::bail!("--strict and --lenient only support uncompressed files, not stdin")
+        },
+        Some(_) if histograms => anyhow::bail!("--strict and --lenient do not support percentiles"),
+        Some(validation) => Box::new(
+            Validating::new(validation, threads).reject_crlf(cli.reject_crlf)
+        ),
+        None => match (cli.strategy, streamed) {
+            (None | Some(Strategy::Streaming), true) => {
+                Box::new(Streaming::new(threads).histograms(histograms))
+            },
+            (Some(strategy), true) => anyhow::bail!(
+                "the {} strategy cannot read stdin or compressed input, use streaming",
+                strategy.name(),
+            ),
+            (strategy, false) => match strategy.unwrap_or(Strategy::MultithreadedRayon) {
+                Strategy::MultithreadedRayon if histograms => {
+                    Box::new(MultithreadedRayon::new(threads).histograms(true))
+                },
+                Strategy::Streaming => Box::new(Streaming::new(threads).histograms(histograms)),
+                strategy if histograms => anyhow::bail!(
+                    "the {} strategy does not support percentiles, use multithreaded_rayon or streaming",
+                    strategy.name(),
+                ),
+                Strategy::Segmented => Box::new(Segmented::new(cli.segment_size)),
+                strategy => strategy.aggregator(threads),
+            },
+        },
+    };
+
+    let weather_stations = aggregator
+        .aggregate(input_file)
+        .with_context(|| format!("failed to process {input_file}"))?;
+    if weather_stations.rejected() > 0 {
+        eprintln!("skipped {} malformed lines", weather_stations.rejected());
+    }
+    let weather_stations = match cli.require_data {
+        true => weather_stations.ensure_not_empty().with_context(|| input_file.to_owned())?,
+        false => weather_stations,
+    };
+    export(cli, &weather_stations)?;
+    let stdout = BufWriter::new(std::io::stdout().lock());
+    write_stats(&weather_stations, cli.format, cli.precision.into(), &cli.columns, stdout)?;
+    Ok(())
+}
+
+fn aggregate_many(cli: &Cli, inputs: &[PathBuf], threads: usize) -> Result<()> {
+    if cli.strict || cli.lenient {
+        anyhow::bail!("--strict and --lenient only support a single input file");
+    }
+    if cli.columns.iter().any(| column | column.needs_histogram()) {
+        anyhow::bail!("percentiles only support a single input file");
+    }
+    let report = aggregate_files(inputs, threads)?;
+    let total = match cli.require_data {
+        true => report.total.ensure_not_empty()?,
+        false => report.total,
+    };
+    export(cli, &total)?;
+
+    let (format, precision) = (cli.format, cli.precision.into());
+    let stdout = BufWriter::new(std::io::stdout().lock());
+    match cli.per_file {
+        true => write_files(&report.files, &total, format, precision, &cli.columns, stdout)?,
+        false => write_stats(&total, format, precision, &cli.columns, stdout)?,
+    }
+    Ok(())
+}
+
+/// Write the `--arrow` and `--parquet` files that were asked for.
+#[cfg(feature = "arrow")]
+fn export(cli: &Cli, weather_stations: &StationStats) -> Result<()> {
+    if let Some(path) = &cli.arrow {
+        brc::export::write_arrow(weather_stations, path)?;
+    }
+    if let Some(path) = &cli.parquet {
+        brc::export::write_parquet(weather_stations, path)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "arrow"))]
+fn export(_cli: &Cli, _weather_stations: &StationStats) -> Result<()> {
+    Ok(())
+}
+
+/// Parse a byte count such as `65536`, `64K` or `16M`.
+fn parse_size(size: &str) -> Result<usize> {
+    let (digits, multiplier) = match size.char_indices().last() {
+        Some((index, 'K' | 'k')) => (&size[..index], 1 << 10),
+        Some((index, 'M' | 'm')) => (&size[..index], 1 << 20),
+        Some((index, 'G' | 'g')) => (&size[..index], 1 << 30),
+        _ => (size, 1),
+    };
+    let bytes = digits
+        .parse::<usize>()?
+        .checked_mul(multiplier)
+        .context("size is too large")?;
+    match bytes {
+        0 => anyhow::bail!("size must be at least one byte"),
+        bytes => Ok(bytes),
+    }
 }
@@ -0,0 +1,68 @@
+/// Running statistics of a single weather station, in tenths of a degree.
+///
+/// `sum` and `count` are 64-bit so billions of rows cannot overflow them.
+/// `sum_of_squares` is kept as an exact integer, so the variance is computed
+/// in one pass without the cancellation of the floating point textbook formula
+/// and partial results merge without any loss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Measurement {
+    pub minimum: i32,
+    pub maximum: i32,
+    pub count: u64,
+    pub sum: i64,
+    pub sum_of_squares: u64
+}
+
+impl Measurement {
+
+    pub fn new(value: i32) -> Self {
+        Self {
+            minimum: value,
+            maximum: value,
+            count: 1,
+            sum: value as i64,
+            sum_of_squares: (value as i64 * value as i64) as u64,
+        }
+    }
+
+    #[inline]
+    pub fn update(&mut self, value: i32) {
+        self.minimum = self.minimum.min(value);
+        self.maximum = self.maximum.max(value);
+        self.count += 1;
+        self.sum += value as i64;
+        self.sum_of_squares += (value as i64 * value as i64) as u64;
+    }
+
+    /// The mean in tenths of a degree, rounded half toward positive infinity
+    /// like the challenge's reference implementation.
+    pub fn rounded_mean(&self) -> i64 {
+        let count = self.count as i64;
+        (2 * self.sum + count).div_euclid(2 * count)
+    }
+
+    /// The population variance in hundredths of a square degree.
+    ///
+    /// `count * sum_of_squares - sum²` is evaluated exactly in 128 bits before
+    /// the single division.
+    pub fn variance(&self) -> f64 {
+        let count = self.count as i128;
+        let spread = count * self.sum_of_squares as i128 - self.sum as i128 * self.sum as i128;
+        spread as f64 / (count * count) as f64
+    }
+
+    #[inline]
+    pub fn merge(&mut self, other: &Self) {
+        self.minimum = self.minimum.min(other.minimum);
+        self.maximum = self.maximum.max(other.maximum);
+        self.count += other.count;
+        self.sum += other.sum;
+        self.sum_of_squares += other.sum_of_squares;
+    }
+}
+
+impl Default for Measurement {
+    fn default() -> Self {
+        Self { minimum: i32::MAX, maximum: i32::MIN, count: 0, sum: 0, sum_of_squares: 0 }
+    }
+}
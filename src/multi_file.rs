@@ -0,0 +1,143 @@
+//! Aggregation of several measurement files into one report.
+//!
+//! Every file is cut into chunks with [`find_chunk_boundaries`], in proportion
+//! to its size, and the chunks of all files share the rayon pool. Stdin and
+//! compressed files can't be split and run as a single streamed task each.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use hashbrown::HashMap;
+use rayon::prelude::*;
+
+use crate::{StationStats, sort_stations};
+use crate::chunked_reading::{MeasurementsMap, find_chunk_boundaries, merge, read_chunk};
+use crate::compression::Compression;
+use crate::streaming::{self, STDIN};
+
+/// Statistics of one input file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStats {
+    pub path: PathBuf,
+    pub stats: StationStats,
+}
+
+/// Combined statistics of all input files, along with the per-file breakdown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultiFileStats {
+    pub total: StationStats,
+    pub files: Vec<FileStats>,
+}
+
+/// Expand directories to the files they contain and glob patterns to their
+/// matches, both in sorted order. Other inputs are kept as they are. A
+/// directory without files or a pattern without matches is an error.
+pub fn expand_inputs(inputs: &[String]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)
+                .with_context(|| format!("failed to list {input}"))?
+                .map(| entry | entry.map(| entry | entry.path()))
+                .collect::<io::Result<Vec<PathBuf>>>()?;
+            entries.retain(| entry | entry.is_file());
+            if entries.is_empty() {
+                bail!("no files in directory {input}");
+            }
+            entries.sort();
+            paths.extend(entries);
+        } else if input.contains(['*', '?', '[']) {
+            let matches = glob::glob(input)?
+                .collect::<Result<Vec<PathBuf>, _>>()?
+                .into_iter()
+                .filter(| entry | entry.is_file())
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                bail!("no files match {input}");
+            }
+            paths.extend(matches);
+        } else {
+            paths.push(path.to_owned());
+        }
+    }
+    Ok(paths)
+}
+
+enum Task<'a> {
+    Chunk { file: usize, path: &'a str, start: usize, end: usize },
+    Stream { file: usize, path: &'a str },
+}
+
+/// Aggregate `paths` on the rayon pool, splitting them into about `threads` chunks in total.
+pub fn aggregate_files(paths: &[PathBuf], threads: usize) -> Result<MultiFileStats> {
+    let names = paths
+        .iter()
+        .map(| path | path.to_str().with_context(|| format!("{} is not valid UTF-8", path.display())))
+        .collect::<Result<Vec<&str>>>()?;
+
+    let mut sizes = Vec::with_capacity(names.len());
+    for &path in &names {
+        let streamed = path == STDIN || Compression::of_file(path)
+            .with_context(|| format!("failed to process {path}"))?
+            .is_some();
+        let size = match streamed {
+            true => None,
+            false => Some(fs::metadata(path)?.len() as usize),
+        };
+        sizes.push(size);
+    }
+    let total_size = sizes.iter().flatten().sum::<usize>().max(1);
+
+    let mut tasks = Vec::new();
+    for (file, (&path, size)) in names.iter().zip(sizes).enumerate() {
+        let Some(size) = size else {
+            tasks.push(Task::Stream { file, path });
+            continue;
+        };
+        let chunks = (threads * size).div_ceil(total_size).clamp(1, threads.max(1));
+        for (start, end) in find_chunk_boundaries(path, chunks)? {
+            tasks.push(Task::Chunk { file, path, start, end });
+        }
+    }
+
+    let parts = tasks
+        .into_par_iter()
+        .map(
+            | task | match task {
+                Task::Chunk { file, path, start, end } => {
+                    let part = read_chunk(path, start, end)
+                        .with_context(|| format!("failed to process {path}"))?;
+                    Ok((file, part))
+                },
+                Task::Stream { file, path } => {
+                    let stats = streaming::brc(path, threads)
+                        .with_context(|| format!("failed to process {path}"))?;
+                    let part = stats
+                        .iter()
+                        .map(| station | (station.name().as_bytes().into(), *station.measurement()))
+                        .collect();
+                    Ok((file, part))
+                },
+            }
+        )
+        .collect::<Result<Vec<(usize, MeasurementsMap)>>>()?;
+
+    let mut per_file: Vec<MeasurementsMap> = (0..paths.len()).map(| _ | HashMap::default()).collect();
+    for (file, part) in parts {
+        merge(&mut per_file[file], &part);
+    }
+    let mut total = MeasurementsMap::default();
+    for part in &per_file {
+        merge(&mut total, part);
+    }
+
+    let files = paths
+        .iter()
+        .zip(per_file)
+        .map(| (path, part) | Ok(FileStats { path: path.clone(), stats: sort_stations(part)? }))
+        .collect::<Result<_>>()?;
+    Ok(MultiFileStats { total: sort_stations(total)?, files })
+}
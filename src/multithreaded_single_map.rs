@@ -1,179 +1,95 @@
-use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::Write;
-use std::sync::{Arc, Mutex};
+use std::sync::Mutex;
 
-use ahash::AHashMap as HashMap;
+use ahash::{AHashMap as HashMap, RandomState};
 use anyhow::Result as Result;
-use bstr::ByteSlice;
 use memmap2::MmapOptions;
 use rayon::prelude::*;
 
-const NEWLINE: u8 = 10;
-const SEMICOLON: u8 = 59;
-const MINUS: u8 = 45;
-const PERIOD: u8 = 46;
+use crate::{Aggregator, Measurement, StationStats, sort_stations};
+use crate::parse::{records, split_at_newlines};
 
-type MeasurementsMap<'a> = Arc<Mutex<HashMap<&'a [u8], Measurement>>>;
+type Shard<'a> = Mutex<HashMap<&'a [u8], Measurement>>;
 
-
-struct Measurement {
-    minimum: i32,
-    maximum: i32,
-    sum: i32,
-    count: i32
+/// Memory map the file and merge every chunk into one shared map, split into
+/// shards by station hash so chunks can merge into different shards at once.
+pub struct MultithreadedSingleMap {
+    cores: usize
 }
 
-impl Measurement {
-    
-    fn new(value: i32) -> Self { 
-        Self { minimum: value, maximum: value, sum: value, count: 1 }
-    }
-    fn update(&mut self, value: i32) {
-        self.minimum = self.minimum.min(value);
-        self.maximum = self.maximum.max(value);
-        self.sum += value;
-        self.count += 1;
-    }
-    
-    fn merge(&mut self, other: &Self) {
-        self.minimum = self.minimum.min(other.minimum);
-        self.maximum = self.maximum.max(other.maximum);
-        self.sum += other.sum;
-        self.count += other.count;
+impl MultithreadedSingleMap {
+    pub fn new(cores: usize) -> Self {
+        Self { cores }
     }
 }
 
-impl Display for Measurement {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let min = self.minimum as f32 * 0.1;
-        let max = self.maximum as f32 * 0.1;
-        let avg = self.sum as f32 / self.count as f32 * 0.1;
-
-        write!(f, "{:.1}/{:.1}/{:.1}", min, avg, max)
+impl Aggregator for MultithreadedSingleMap {
+    fn aggregate(&self, file_path: &str) -> Result<StationStats> {
+        brc(file_path, self.cores)
     }
 }
 
-pub fn brc(file_path: &str) -> Result<()> {
-    let cores: usize = std::thread::available_parallelism().unwrap().into();
-
+pub fn brc(file_path: &str, cores: usize) -> Result<StationStats> {
     let file: File = File::open(file_path)?;
     let mmap = unsafe { MmapOptions::new().map(&file)? };
-    let size: usize = mmap.len();
 
-    let chunk_size: usize = size / cores;
-    let mut starts: Vec<usize> = (0..cores)
-        .map(| core | core * chunk_size)
+    let shards: Vec<Shard> = (0..(4 * cores).next_power_of_two())
+        .map(| _ | Mutex::default())
         .collect();
+    let hasher = RandomState::new();
 
-    for i in 1..cores {
-        starts[i] = find_next_newline(starts[i], &mmap);
-    }
-
-    let mut ends: Vec<usize> = vec![0; cores];
-    ends[..(cores - 1)].copy_from_slice(&starts[1..cores]);
-    ends[cores - 1] = size;
-
-    let measurements: MeasurementsMap = Arc::new(Mutex::new(HashMap::new()));
-
-    let chunks: Vec<(usize, usize)> = starts.into_iter().zip(ends).collect();
+    let chunks: Vec<(usize, usize)> = split_at_newlines(&mmap, cores);
 
     chunks
         .into_par_iter()
+        .enumerate()
         .for_each(
-            | (start, end ) | {
-                let map = measurements.clone();
-                scan_ascii_chunk(start, end, &mmap, map);
+            | (index, (start, end)) | {
+                scan_ascii_chunk(start, end, &mmap, index, &shards, &hasher);
             }
         );
-    
-    if let Ok(data) = measurements.lock() {
-        let mut weather_stations: Vec<_> = data.iter().collect();
-        weather_stations.sort_unstable_by_key(|item| item.0);
-        write_output(weather_stations)?;
-    }
 
-    Ok(())
-}
-
-fn find_next_newline(start: usize, buffer: &[u8]) -> usize {
-    match buffer[start..].find_byte(NEWLINE) {
-        Some(position) => start + position + 1,
-        None => unreachable!()
-    }
+    let weather_stations = shards
+        .into_iter()
+        .flat_map(| shard | shard.into_inner().expect("no scan panicked while holding a lock"));
+    sort_stations(weather_stations)
 }
 
 fn scan_ascii_chunk<'a>(
-    start: usize, end: usize, buffer: &'a [u8], shared_measurements: MeasurementsMap<'a>
+    start: usize,
+    end: usize,
+    buffer: &'a [u8],
+    index: usize,
+    shards: &[Shard<'a>],
+    hasher: &RandomState,
 ) {
     let mut new_measurements: HashMap<&[u8], Measurement> = HashMap::new();
     
-    let mut line_start = start;
-    let mut name_end = start;
-
-    for (position, &character) in buffer[start..end].iter().enumerate() {
-        match character {
-            SEMICOLON => {
-                name_end = start + position;
-            },
-            NEWLINE => {
-                let station = &buffer[line_start..name_end];
-                let value = parse_ascii_to_int(
-                    &buffer[(name_end + 1)..(position + start)]
-                );
-                new_measurements
-                    .entry(station)
-                    .and_modify(| item | item.update(value))
-                    .or_insert_with(|| Measurement::new(value));
-                
-                line_start = start + position + 1;
-            },
-            _ => continue
-        };
-    }
-    
-    if let Ok(mut measurements) = shared_measurements.lock() {
-        for (station, data) in new_measurements.into_iter() {
-            measurements
-                .entry(station)
-                .and_modify(| item | item.merge(&data))
-                .or_insert_with(|| data);
-        }
-    }
-}
-
-fn parse_ascii_to_int(buffer: &[u8]) -> i32 {
-    let mut acc: i32 = 0;
-    let mut is_neg = false;
-
-    for &val in buffer {
-        match val {
-            PERIOD => continue,
-            MINUS => is_neg = true,
-            _ => acc = acc * 10 + val as i32 - 48
-        }
+    for (station, value) in records(&buffer[start..end]) {
+        new_measurements
+            .entry(station)
+            .and_modify(| item | item.update(value))
+            .or_insert_with(|| Measurement::new(value));
     }
 
-    match is_neg {
-        true => -acc,
-        false => acc,
+    let mut partitions = vec![Vec::new(); shards.len()];
+    for (station, data) in new_measurements {
+        partitions[hasher.hash_one(station) as usize & (shards.len() - 1)].push((station, data));
     }
-}
-
-fn write_output(weather_stations: Vec<(&&[u8], &Measurement)>) -> Result<()> {
-    let mut weather_iter = weather_stations.into_iter();
-    let (first_station, first_weather) = weather_iter.next().unwrap();
-    let first_station = std::str::from_utf8(first_station)?;
-
-    let stdout = std::io::stdout();
-    let mut lock = stdout.lock();
 
-    write!(lock, "{{")?;
-    write!(lock, "{first_station}={first_weather}")?;
-    for (station, weather) in  weather_iter {
-        let station = std::str::from_utf8(station)?;
-        write!(lock, ", {station}={weather}")?;
+    // Every chunk starts at a different shard to spread the lock contention.
+    for offset in 0..shards.len() {
+        let shard = (index + offset) & (shards.len() - 1);
+        if partitions[shard].is_empty() {
+            continue;
+        }
+        if let Ok(mut measurements) = shards[shard].lock() {
+            for (station, data) in partitions[shard].drain(..) {
+                measurements
+                    .entry(station)
+                    .and_modify(| item | item.merge(&data))
+                    .or_insert_with(|| data);
+            }
+        }
     }
-    writeln!(lock, "}}")?;
-    Ok(())
 }
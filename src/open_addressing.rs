@@ -0,0 +1,233 @@
+use std::fs::File;
+
+use anyhow::{Result, bail};
+use memmap2::MmapOptions;
+use rayon::prelude::*;
+
+use crate::{Aggregator, Measurement, StationStats, sort_stations};
+use crate::parse::{CARRIAGE_RETURN, NEWLINE, parse_temperature, split_at_newlines};
+
+/// Slots of every table, a power of two.
+const SLOTS: usize = 1 << 14;
+
+/// Stations a table accepts before it is considered full, keeping probe sequences short.
+pub const MAX_STATIONS: usize = SLOTS / 4 * 3;
+
+/// Names up to this many bytes are compared using the inline key alone.
+const INLINE_LENGTH: usize = 16;
+
+const SEMICOLONS: u64 = 0x3B3B_3B3B_3B3B_3B3B;
+const NEWLINES: u64 = 0x0A0A_0A0A_0A0A_0A0A;
+const LOW_BITS: u64 = 0x0101_0101_0101_0101;
+const HIGH_BITS: u64 = 0x8080_8080_8080_8080;
+const HASH_MULTIPLIER: u64 = 0x517C_C1B7_2722_0A95;
+
+/// Memory map the file and aggregate every chunk into a fixed-capacity
+/// open-addressing table, hashing each name while searching for its `;`.
+pub struct OpenAddressing {
+    threads: usize
+}
+
+impl OpenAddressing {
+    pub fn new(threads: usize) -> Self {
+        Self { threads }
+    }
+}
+
+impl Aggregator for OpenAddressing {
+    fn aggregate(&self, file_path: &str) -> Result<StationStats> {
+        brc(file_path, self.threads)
+    }
+}
+
+pub fn brc(file_path: &str, threads: usize) -> Result<StationStats> {
+    let file: File = File::open(file_path)?;
+    let mmap = unsafe { MmapOptions::new().map(&file)? };
+
+    let weather_stations = split_at_newlines(&mmap, threads)
+        .into_par_iter()
+        .map(| (start, end) | scan_chunk(&mmap[start..end]))
+        .try_reduce(
+            StationTable::new,
+            | mut weather_stations, part | {
+                weather_stations.merge(&part)?;
+                Ok(weather_stations)
+            }
+        )?;
+
+    sort_stations(weather_stations.iter().map(| (name, measurement) | (name, *measurement)))
+}
+
+fn scan_chunk(buffer: &[u8]) -> Result<StationTable<'_>> {
+    let mut table = StationTable::new();
+
+    let mut line_start = 0;
+    while line_start < buffer.len() {
+        let Some((name_end, key, hash)) = scan_name(buffer, line_start) else { break };
+        // Lines without a separator are skipped.
+        if buffer[name_end] == NEWLINE {
+            line_start = name_end + 1;
+            continue;
+        }
+        let name = &buffer[line_start..name_end];
+        let (value, length) = parse_temperature(&buffer[(name_end + 1)..]);
+        table.update(name, key, hash, value)?;
+
+        let number_end = name_end + 1 + length;
+        let skip_return = (buffer.get(number_end) == Some(&CARRIAGE_RETURN)) as usize;
+        line_start = number_end + skip_return + 1;
+    }
+    Ok(table)
+}
+
+/// Find the `;` ending the name at `start` eight bytes at a time, hashing the
+/// name and collecting its first sixteen bytes on the way. Stops early at a
+/// newline when the line has no separator.
+#[inline]
+fn scan_name(buffer: &[u8], start: usize) -> Option<(usize, [u64; 2], u64)> {
+    let mut key = [0u64; 2];
+    let mut hash = 0u64;
+    let mut position = start;
+    loop {
+        if position >= buffer.len() {
+            return None;
+        }
+        let word = load_word(buffer, position);
+        let matches = zero_bytes(word ^ SEMICOLONS) | zero_bytes(word ^ NEWLINES);
+
+        let (word, length) = match matches {
+            0 => (word, 8),
+            _ => {
+                // The lowest match is exact, borrows only create false matches above it.
+                let length = matches.trailing_zeros() as usize / 8;
+                (word & (1u64 << (length * 8)).wrapping_sub(1), length)
+            }
+        };
+        let word_index = (position - start) / 8;
+        if word_index < key.len() {
+            key[word_index] = word;
+        }
+        hash = (hash.rotate_left(5) ^ word).wrapping_mul(HASH_MULTIPLIER);
+
+        if length < 8 {
+            return Some((position + length, key, hash));
+        }
+        position += 8;
+    }
+}
+
+/// High bit set in the bytes of `word` that are zero, and possibly in bytes above
+/// the lowest zero byte, where borrows create false matches.
+#[inline]
+fn zero_bytes(word: u64) -> u64 {
+    word.wrapping_sub(LOW_BITS) & !word & HIGH_BITS
+}
+
+/// Eight bytes at `position` as a little endian word, zero padded past the end.
+#[inline]
+fn load_word(buffer: &[u8], position: usize) -> u64 {
+    match buffer[position..].first_chunk::<8>() {
+        Some(bytes) => u64::from_le_bytes(*bytes),
+        None => {
+            let mut bytes = [0u8; 8];
+            bytes[..(buffer.len() - position)].copy_from_slice(&buffer[position..]);
+            u64::from_le_bytes(bytes)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Slot<'a> {
+    hash: u64,
+    key: [u64; 2],
+    name: &'a [u8],
+    measurement: Measurement,
+}
+
+impl Slot<'_> {
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.measurement.count == 0
+    }
+}
+
+/// Linear probing table of station names borrowed from the input.
+///
+/// Names of up to [`INLINE_LENGTH`] bytes are identified by their hash and
+/// inline key, longer ones fall back to comparing the whole name.
+pub struct StationTable<'a> {
+    slots: Box<[Slot<'a>]>,
+    len: usize,
+}
+
+impl<'a> StationTable<'a> {
+    pub fn new() -> Self {
+        Self { slots: vec![Slot::default(); SLOTS].into_boxed_slice(), len: 0 }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    fn update(&mut self, name: &'a [u8], key: [u64; 2], hash: u64, value: i32) -> Result<()> {
+        let slot = self.find(name, key, hash)?;
+        slot.measurement.update(value);
+        Ok(())
+    }
+
+    /// Add the measurements of `other` to this table.
+    pub fn merge(&mut self, other: &StationTable<'a>) -> Result<()> {
+        for slot in other.slots.iter().filter(| slot | !slot.is_empty()) {
+            self.find(slot.name, slot.key, slot.hash)?.measurement.merge(&slot.measurement);
+        }
+        Ok(())
+    }
+
+    /// The slot of `name`, claiming an empty one if the name is new.
+    #[inline]
+    fn find(&mut self, name: &'a [u8], key: [u64; 2], hash: u64) -> Result<&mut Slot<'a>> {
+        let mut index = (hash >> (64 - SLOTS.trailing_zeros())) as usize;
+        loop {
+            let slot = &self.slots[index];
+            if slot.is_empty() {
+                break;
+            }
+            if slot.hash == hash
+                && slot.key == key
+                && slot.name.len() == name.len()
+                && (name.len() <= INLINE_LENGTH || slot.name == name)
+            {
+                return Ok(&mut self.slots[index]);
+            }
+            index = (index + 1) & (SLOTS - 1);
+        }
+
+        if self.len == MAX_STATIONS {
+            bail!("more than {MAX_STATIONS} distinct stations");
+        }
+        // The slot stays empty until the caller records a measurement in it.
+        self.len += 1;
+        let slot = &mut self.slots[index];
+        *slot = Slot { hash, key, name, measurement: Measurement::default() };
+        Ok(slot)
+    }
+
+    /// Stations in table order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'a [u8], &Measurement)> {
+        self.slots
+            .iter()
+            .filter(| slot | !slot.is_empty())
+            .map(| slot | (slot.name, &slot.measurement))
+    }
+}
+
+impl Default for StationTable<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
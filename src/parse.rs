@@ -0,0 +1,142 @@
+//! Byte level helpers shared by the strategies.
+
+use bstr::ByteSlice;
+
+pub const NEWLINE: u8 = 10;
+pub const SEMICOLON: u8 = 59;
+pub const MINUS: u8 = 45;
+pub const PERIOD: u8 = 46;
+pub const CARRIAGE_RETURN: u8 = 13;
+
+/// UTF-8 byte order mark, written at the start of files by some Windows tools.
+pub const BYTE_ORDER_MARK: &[u8] = b"\xEF\xBB\xBF";
+
+/// Length of a leading byte order mark in `buffer`, or zero without one.
+#[inline]
+pub fn bom_length(buffer: &[u8]) -> usize {
+    match buffer.starts_with(BYTE_ORDER_MARK) {
+        true => BYTE_ORDER_MARK.len(),
+        false => 0,
+    }
+}
+
+/// Parse a temperature such as `-12.3` into tenths of a degree, ignoring the
+/// `\r` of a `\r\n` line ending.
+#[inline]
+pub fn parse_ascii_to_int(buffer: &[u8]) -> i32 {
+    let mut acc: i32 = 0;
+    let mut is_neg = false;
+
+    for &val in buffer {
+        match val {
+            PERIOD | CARRIAGE_RETURN => continue,
+            MINUS => is_neg = true,
+            _ => acc = acc * 10 + val as i32 - 48
+        }
+    }
+
+    match is_neg {
+        true => -acc,
+        false => acc,
+    }
+}
+
+/// Parse the temperature at the start of `buffer` without branching on its
+/// digits, returning tenths of a degree and the length of the number.
+///
+/// Expects the `-?\d?\d\.\d` format, anything following the number is ignored.
+/// Buffers shorter than eight bytes are copied to a padded word first.
+#[inline]
+pub fn parse_temperature(buffer: &[u8]) -> (i32, usize) {
+    let word = match buffer.first_chunk::<8>() {
+        Some(bytes) => u64::from_le_bytes(*bytes),
+        None => {
+            let mut bytes = [0u8; 8];
+            bytes[..buffer.len()].copy_from_slice(buffer);
+            u64::from_le_bytes(bytes)
+        }
+    };
+    parse_swar(word)
+}
+
+/// SIMD within a register parser over eight little endian bytes.
+///
+/// Digits have bit 4 set while `.` and `-` do not, so the lowest clear bit 4
+/// of bytes one to three marks the decimal point. The digits are then shifted
+/// to fixed positions and combined with a single multiplication.
+#[inline]
+pub fn parse_swar(word: u64) -> (i32, usize) {
+    let period = (!word & 0x1010_1000).trailing_zeros();
+    // All ones for a leading minus, zero otherwise.
+    let sign = ((!word << 59) as i64 >> 63) as u64;
+    let digits = ((word & !(sign & 0xFF)) << (28 - period)) & 0x0F_000F_0F00;
+    let absolute = (digits.wrapping_mul(0x640A_0001) >> 32) & 0x3FF;
+    let value = (absolute ^ sign).wrapping_sub(sign) as i32;
+    (value, (period as usize >> 3) + 2)
+}
+
+/// Iterate the `station;temperature` records of `buffer` using [`parse_temperature`].
+///
+/// `buffer` must start at a record and end after a newline or at the end of
+/// the input. A `\r` before the newline is skipped and a final record without
+/// a separator is ignored.
+pub fn records(buffer: &[u8]) -> Records<'_> {
+    Records { buffer, position: 0 }
+}
+
+pub struct Records<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = (&'a [u8], i32);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = self.buffer.get(self.position..)?;
+        let mut name_end = line.find_byteset([SEMICOLON, NEWLINE])?;
+        // Lines without a separator are skipped.
+        while line[name_end] == NEWLINE {
+            self.position += name_end + 1;
+            line = &line[(name_end + 1)..];
+            name_end = line.find_byteset([SEMICOLON, NEWLINE])?;
+        }
+        let (value, length) = parse_temperature(&line[(name_end + 1)..]);
+
+        let number_end = name_end + 1 + length;
+        let skip_return = (line.get(number_end) == Some(&CARRIAGE_RETURN)) as usize;
+        self.position += number_end + skip_return + 1;
+        Some((&line[..name_end], value))
+    }
+}
+
+/// Split a final record that is not followed by a newline into station name
+/// and temperature, or `None` if it has no separator.
+pub fn parse_unterminated(record: &[u8]) -> Option<(&[u8], i32)> {
+    let separator = record.find_byte(SEMICOLON)?;
+    Some((&record[..separator], parse_ascii_to_int(&record[(separator + 1)..])))
+}
+
+/// Split `buffer` into at most `parts` consecutive ranges that each end directly
+/// after a newline, except for the last one which ends at the end of the buffer.
+/// A leading byte order mark is left out of the first range.
+pub fn split_at_newlines(buffer: &[u8], parts: usize) -> Vec<(usize, usize)> {
+    let chunk_size = buffer.len() / parts.max(1);
+    let mut chunks = Vec::with_capacity(parts);
+
+    let mut start = bom_length(buffer);
+    for part in 1..parts {
+        let target = (part * chunk_size).max(start);
+        let end = match buffer[target..].find_byte(NEWLINE) {
+            Some(position) => target + position + 1,
+            None => break
+        };
+        chunks.push((start, end));
+        start = end;
+    }
+    if start < buffer.len() || chunks.is_empty() {
+        chunks.push((start, buffer.len()));
+    }
+    chunks
+}
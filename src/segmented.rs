@@ -0,0 +1,78 @@
+use std::fs::File;
+
+use ahash::AHashMap as HashMap;
+use anyhow::Result as Result;
+use memmap2::MmapOptions;
+use rayon::prelude::*;
+
+use crate::{Aggregator, Measurement, StationStats, sort_stations};
+use crate::parse::{records, split_at_newlines};
+
+type MeasurementMap<'a> = HashMap<&'a [u8], Measurement>;
+
+/// Default length of the segments handed out to rayon.
+pub const DEFAULT_SEGMENT_SIZE: usize = 8 * 1024 * 1024;
+
+/// Memory map the file and cut it into many small segments, so rayon's work
+/// stealing can balance slow cores or page fault heavy regions. Every worker
+/// folds the segments it runs into its own map, merged at the end.
+pub struct Segmented {
+    segment_size: usize
+}
+
+impl Segmented {
+    pub fn new(segment_size: usize) -> Self {
+        Self { segment_size }
+    }
+}
+
+impl Default for Segmented {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEGMENT_SIZE)
+    }
+}
+
+impl Aggregator for Segmented {
+    fn aggregate(&self, file_path: &str) -> Result<StationStats> {
+        brc(file_path, self.segment_size)
+    }
+}
+
+pub fn brc(file_path: &str, segment_size: usize) -> Result<StationStats> {
+    let file: File = File::open(file_path)?;
+    let mmap = unsafe { MmapOptions::new().map(&file)? };
+
+    let segments = mmap.len().div_ceil(segment_size.max(1));
+    let weather_stations = split_at_newlines(&mmap, segments)
+        .into_par_iter()
+        .fold(
+            MeasurementMap::default,
+            | mut measurements, (start, end) | {
+                scan_segment(&mmap[start..end], &mut measurements);
+                measurements
+            }
+        )
+        .reduce(
+            MeasurementMap::default,
+            | mut weather_stations, part | {
+                for (name, value) in part {
+                    weather_stations
+                        .entry(name)
+                        .and_modify(| item | item.merge(&value))
+                        .or_insert_with(|| value);
+                }
+                weather_stations
+            }
+        );
+
+    sort_stations(weather_stations)
+}
+
+fn scan_segment<'a>(buffer: &'a [u8], measurements: &mut MeasurementMap<'a>) {
+    for (station, value) in records(buffer) {
+        measurements
+            .entry(station)
+            .and_modify(| item | item.update(value))
+            .or_insert_with(|| Measurement::new(value));
+    }
+}
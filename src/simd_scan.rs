@@ -0,0 +1,208 @@
+use std::fs::File;
+
+use ahash::AHashMap as HashMap;
+use anyhow::Result as Result;
+use memmap2::MmapOptions;
+use rayon::prelude::*;
+
+use crate::{Aggregator, Measurement, StationStats, sort_stations};
+use crate::parse::{NEWLINE, SEMICOLON, parse_temperature, parse_unterminated, split_at_newlines};
+
+type MeasurementMap<'a> = HashMap<&'a [u8], Measurement>;
+
+/// Bytes classified per scanner call, one bit per byte in the masks.
+const BLOCK_SIZE: usize = 64;
+
+/// Memory map the file and find separators with vector instructions,
+/// one chunk per rayon thread.
+pub struct SimdScan {
+    threads: usize,
+    scanner: Scanner,
+}
+
+impl SimdScan {
+    pub fn new(threads: usize) -> Self {
+        Self { threads, scanner: Scanner::detect() }
+    }
+
+    /// Use `scanner` instead of the best one supported by the CPU.
+    pub fn scanner(mut self, scanner: Scanner) -> Self {
+        self.scanner = scanner;
+        self
+    }
+}
+
+impl Aggregator for SimdScan {
+    fn aggregate(&self, file_path: &str) -> Result<StationStats> {
+        brc_with_scanner(file_path, self.threads, self.scanner)
+    }
+}
+
+/// Instruction set used to find `;` and `\n`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scanner {
+    Avx2,
+    Sse2,
+    Portable,
+}
+
+impl Scanner {
+    /// The fastest scanner supported by the running CPU.
+    pub fn detect() -> Self {
+        [Scanner::Avx2, Scanner::Sse2]
+            .into_iter()
+            .find(| scanner | scanner.is_supported())
+            .unwrap_or(Scanner::Portable)
+    }
+
+    pub fn is_supported(self) -> bool {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Scanner::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch = "x86_64")]
+            Scanner::Sse2 => true,
+            Scanner::Portable => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+
+    /// Masks of the `;` and `\n` bytes in `block`, bit `i` standing for `block[i]`.
+    #[inline]
+    fn masks(self, block: &[u8; BLOCK_SIZE]) -> (u64, u64) {
+        match self {
+            // SAFETY: `brc_with_scanner` rejects scanners the CPU does not support.
+            #[cfg(target_arch = "x86_64")]
+            Scanner::Avx2 => unsafe { x86::masks_avx2(block) },
+            #[cfg(target_arch = "x86_64")]
+            Scanner::Sse2 => unsafe { x86::masks_sse2(block) },
+            _ => masks_portable(block),
+        }
+    }
+}
+
+pub fn brc(file_path: &str, threads: usize) -> Result<StationStats> {
+    brc_with_scanner(file_path, threads, Scanner::detect())
+}
+
+fn brc_with_scanner(file_path: &str, threads: usize, scanner: Scanner) -> Result<StationStats> {
+    if !scanner.is_supported() {
+        anyhow::bail!("the {scanner:?} scanner is not supported by this CPU");
+    }
+    let file: File = File::open(file_path)?;
+    let mmap = unsafe { MmapOptions::new().map(&file)? };
+
+    let weather_stations = split_at_newlines(&mmap, threads)
+        .into_par_iter()
+        .map(| (start, end) | scan_chunk(&mmap[start..end], scanner))
+        .reduce(
+            HashMap::default,
+            | mut weather_stations, part | {
+                for (name, value) in part {
+                    weather_stations
+                        .entry(name)
+                        .and_modify(| item: &mut Measurement | item.merge(&value))
+                        .or_insert_with(|| value);
+                }
+                weather_stations
+            }
+        );
+
+    sort_stations(weather_stations)
+}
+
+fn scan_chunk(buffer: &[u8], scanner: Scanner) -> MeasurementMap<'_> {
+    let mut measurements: MeasurementMap = HashMap::default();
+
+    let mut line_start = 0;
+    let mut name_end = None;
+    for offset in (0..buffer.len()).step_by(BLOCK_SIZE) {
+        let (semicolons, newlines) = match buffer[offset..].first_chunk::<BLOCK_SIZE>() {
+            Some(block) => scanner.masks(block),
+            None => {
+                let mut block = [0u8; BLOCK_SIZE];
+                block[..(buffer.len() - offset)].copy_from_slice(&buffer[offset..]);
+                scanner.masks(&block)
+            }
+        };
+
+        let mut separators = semicolons | newlines;
+        while separators != 0 {
+            let bit = separators.trailing_zeros();
+            let position = offset + bit as usize;
+            separators &= separators - 1;
+
+            if semicolons & (1 << bit) != 0 {
+                name_end = Some(position);
+                continue;
+            }
+            // Lines without a separator are skipped like an unterminated one would be.
+            if let Some(name_end) = name_end.take() {
+                let (value, _) = parse_temperature(&buffer[(name_end + 1)..]);
+                measurements
+                    .entry(&buffer[line_start..name_end])
+                    .and_modify(| item | item.update(value))
+                    .or_insert_with(|| Measurement::new(value));
+            }
+            line_start = position + 1;
+        }
+    }
+
+    if let Some((station, value)) = parse_unterminated(&buffer[line_start..]) {
+        measurements
+            .entry(station)
+            .and_modify(| item | item.update(value))
+            .or_insert_with(|| Measurement::new(value));
+    }
+
+    measurements
+}
+
+fn masks_portable(block: &[u8; BLOCK_SIZE]) -> (u64, u64) {
+    let mut semicolons = 0;
+    let mut newlines = 0;
+    for (index, &byte) in block.iter().enumerate() {
+        semicolons |= ((byte == SEMICOLON) as u64) << index;
+        newlines |= ((byte == NEWLINE) as u64) << index;
+    }
+    (semicolons, newlines)
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::BLOCK_SIZE;
+    use crate::parse::{NEWLINE, SEMICOLON};
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn masks_avx2(block: &[u8; BLOCK_SIZE]) -> (u64, u64) {
+        let semicolon = _mm256_set1_epi8(SEMICOLON as i8);
+        let newline = _mm256_set1_epi8(NEWLINE as i8);
+        let low = _mm256_loadu_si256(block.as_ptr().cast());
+        let high = _mm256_loadu_si256(block.as_ptr().add(32).cast());
+
+        let semicolons_low = _mm256_movemask_epi8(_mm256_cmpeq_epi8(low, semicolon)) as u32 as u64;
+        let semicolons_high = _mm256_movemask_epi8(_mm256_cmpeq_epi8(high, semicolon)) as u32 as u64;
+        let newlines_low = _mm256_movemask_epi8(_mm256_cmpeq_epi8(low, newline)) as u32 as u64;
+        let newlines_high = _mm256_movemask_epi8(_mm256_cmpeq_epi8(high, newline)) as u32 as u64;
+        (semicolons_low | semicolons_high << 32, newlines_low | newlines_high << 32)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn masks_sse2(block: &[u8; BLOCK_SIZE]) -> (u64, u64) {
+        let semicolon = _mm_set1_epi8(SEMICOLON as i8);
+        let newline = _mm_set1_epi8(NEWLINE as i8);
+
+        let mut semicolons = 0;
+        let mut newlines = 0;
+        for lane in 0..(BLOCK_SIZE / 16) {
+            let bytes = _mm_loadu_si128(block.as_ptr().add(lane * 16).cast());
+            let lane_semicolons = _mm_movemask_epi8(_mm_cmpeq_epi8(bytes, semicolon)) as u16 as u64;
+            let lane_newlines = _mm_movemask_epi8(_mm_cmpeq_epi8(bytes, newline)) as u16 as u64;
+            semicolons |= lane_semicolons << (lane * 16);
+            newlines |= lane_newlines << (lane * 16);
+        }
+        (semicolons, newlines)
+    }
+}
@@ -0,0 +1,214 @@
+use anyhow::Result;
+
+use crate::Measurement;
+use crate::error::NoData;
+use crate::histogram::Histogram;
+
+/// Aggregated statistics of a single weather station.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Station {
+    name: String,
+    measurement: Measurement,
+    histogram: Option<Histogram>
+}
+
+impl Station {
+    pub fn new(name: String, measurement: Measurement) -> Self {
+        Self { name, measurement, histogram: None }
+    }
+
+    /// Like [`Station::new`], with every value counted for percentiles.
+    pub fn with_histogram(name: String, measurement: Measurement, histogram: Histogram) -> Self {
+        Self { name, measurement, histogram: Some(histogram) }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The raw statistics, in tenths of a degree.
+    pub fn measurement(&self) -> &Measurement {
+        &self.measurement
+    }
+
+    /// Every value of the station, when it was aggregated with histograms.
+    pub fn histogram(&self) -> Option<&Histogram> {
+        self.histogram.as_ref()
+    }
+
+    pub fn min(&self) -> f64 {
+        self.measurement.minimum as f64 / 10.
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.measurement.sum as f64 / self.measurement.count as f64 / 10.
+    }
+
+    pub fn max(&self) -> f64 {
+        self.measurement.maximum as f64 / 10.
+    }
+
+    pub fn count(&self) -> u64 {
+        self.measurement.count
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.measurement.sum as f64 / 10.
+    }
+
+    /// Population variance, in square degrees.
+    pub fn variance(&self) -> f64 {
+        self.measurement.variance() / 100.
+    }
+
+    /// Population standard deviation, in degrees.
+    pub fn std_dev(&self) -> f64 {
+        self.variance().sqrt()
+    }
+
+    /// The nearest-rank `percentile` in degrees, `None` unless the station was
+    /// aggregated with histograms.
+    pub fn percentile(&self, percentile: f64) -> Option<f64> {
+        Some(self.histogram.as_ref()?.percentile(percentile)? as f64 / 10.)
+    }
+}
+
+/// Per station statistics, sorted by station name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StationStats {
+    stations: Vec<Station>,
+    rejected: u64
+}
+
+impl StationStats {
+    /// Number of malformed lines that were skipped instead of aggregated.
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    pub(crate) fn set_rejected(&mut self, rejected: u64) {
+        self.rejected = rejected;
+    }
+
+    pub fn len(&self) -> usize {
+        self.stations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stations.is_empty()
+    }
+
+    /// Turn an empty result into a [`NoData`] error.
+    pub fn ensure_not_empty(self) -> Result<Self, NoData> {
+        match self.is_empty() {
+            true => Err(NoData),
+            false => Ok(self),
+        }
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Station> {
+        self.stations.iter()
+    }
+
+    pub fn as_slice(&self) -> &[Station] {
+        &self.stations
+    }
+
+    /// Look up a station by name.
+    pub fn get(&self, name: &str) -> Option<&Station> {
+        self.stations
+            .binary_search_by(| station | station.name.as_str().cmp(name))
+            .ok()
+            .map(| index | &self.stations[index])
+    }
+}
+
+impl IntoIterator for StationStats {
+    type Item = Station;
+    type IntoIter = std::vec::IntoIter<Station>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.stations.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a StationStats {
+    type Item = &'a Station;
+    type IntoIter = std::slice::Iter<'a, Station>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.stations.iter()
+    }
+}
+
+/// What a strategy keeps per station while scanning, a plain [`Measurement`]
+/// or one with a [`Histogram`] for percentiles. Strategies that support both
+/// are generic over it, so the plain scan never touches a histogram.
+pub(crate) trait Accumulator: Send {
+    fn new(value: i32) -> Self;
+    fn update(&mut self, value: i32);
+    fn merge(&mut self, other: &Self);
+    fn into_station(self, name: String) -> Station;
+}
+
+impl Accumulator for Measurement {
+    #[inline]
+    fn new(value: i32) -> Self {
+        Measurement::new(value)
+    }
+
+    #[inline]
+    fn update(&mut self, value: i32) {
+        Measurement::update(self, value)
+    }
+
+    #[inline]
+    fn merge(&mut self, other: &Self) {
+        Measurement::merge(self, other)
+    }
+
+    fn into_station(self, name: String) -> Station {
+        Station::new(name, self)
+    }
+}
+
+impl Accumulator for (Measurement, Histogram) {
+    #[inline]
+    fn new(value: i32) -> Self {
+        let mut histogram = Histogram::new();
+        histogram.add(value);
+        (Measurement::new(value), histogram)
+    }
+
+    #[inline]
+    fn update(&mut self, value: i32) {
+        self.0.update(value);
+        self.1.add(value);
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.0.merge(&other.0);
+        self.1.merge(&other.1);
+    }
+
+    fn into_station(self, name: String) -> Station {
+        Station::with_histogram(name, self.0, self.1)
+    }
+}
+
+/// Convert raw station names to strings and sort the stations by name.
+pub(crate) fn sort_stations<K: AsRef<[u8]>, A: Accumulator>(
+    weather_stations: impl IntoIterator<Item = (K, A)>
+) -> Result<StationStats> {
+    let mut stations = weather_stations
+        .into_iter()
+        .map(
+            | (name, accumulator) | {
+                let name = std::str::from_utf8(name.as_ref())?.to_owned();
+                Ok(accumulator.into_station(name))
+            }
+        )
+        .collect::<Result<Vec<Station>>>()?;
+    stations.sort_unstable_by(| a, b | a.name.cmp(&b.name));
+    Ok(StationStats { stations, rejected: 0 })
+}
@@ -0,0 +1,75 @@
+use clap::ValueEnum;
+
+use crate::Aggregator;
+use crate::chunked_reading::ChunkedReading;
+use crate::first_attempt::FirstAttempt;
+use crate::first_attempt_alternative::FirstAttemptAlternative;
+use crate::first_attempt_vec::FirstAttemptVec;
+use crate::improved_file_read::ImprovedFileRead;
+use crate::multithreaded_manual::MultithreadedManual;
+use crate::multithreaded_rayon::MultithreadedRayon;
+use crate::multithreaded_single_map::MultithreadedSingleMap;
+use crate::open_addressing::OpenAddressing;
+use crate::prototyping::Prototyping;
+use crate::segmented::Segmented;
+use crate::simd_scan::SimdScan;
+use crate::streaming::Streaming;
+
+/// All available implementations, selectable by name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum Strategy {
+    FirstAttempt,
+    FirstAttemptAlternative,
+    FirstAttemptVec,
+    ImprovedFileRead,
+    MultithreadedManual,
+    MultithreadedRayon,
+    ChunkedReading,
+    MultithreadedSingleMap,
+    Prototyping,
+    SimdScan,
+    OpenAddressing,
+    Segmented,
+    Streaming,
+}
+
+impl Strategy {
+    /// The name used on the command line, e.g. `multithreaded_rayon`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::FirstAttempt => "first_attempt",
+            Strategy::FirstAttemptAlternative => "first_attempt_alternative",
+            Strategy::FirstAttemptVec => "first_attempt_vec",
+            Strategy::ImprovedFileRead => "improved_file_read",
+            Strategy::MultithreadedManual => "multithreaded_manual",
+            Strategy::MultithreadedRayon => "multithreaded_rayon",
+            Strategy::ChunkedReading => "chunked_reading",
+            Strategy::MultithreadedSingleMap => "multithreaded_single_map",
+            Strategy::Prototyping => "prototyping",
+            Strategy::SimdScan => "simd_scan",
+            Strategy::OpenAddressing => "open_addressing",
+            Strategy::Segmented => "segmented",
+            Strategy::Streaming => "streaming",
+        }
+    }
+
+    /// Build the aggregator for this strategy, using `threads` workers where supported.
+    pub fn aggregator(self, threads: usize) -> Box<dyn Aggregator> {
+        match self {
+            Strategy::FirstAttempt => Box::new(FirstAttempt),
+            Strategy::FirstAttemptAlternative => Box::new(FirstAttemptAlternative),
+            Strategy::FirstAttemptVec => Box::new(FirstAttemptVec),
+            Strategy::ImprovedFileRead => Box::new(ImprovedFileRead),
+            Strategy::MultithreadedManual => Box::new(MultithreadedManual::new(threads)),
+            Strategy::MultithreadedRayon => Box::new(MultithreadedRayon::new(threads)),
+            Strategy::ChunkedReading => Box::new(ChunkedReading::new(threads)),
+            Strategy::MultithreadedSingleMap => Box::new(MultithreadedSingleMap::new(threads)),
+            Strategy::Prototyping => Box::new(Prototyping::new(threads)),
+            Strategy::SimdScan => Box::new(SimdScan::new(threads)),
+            Strategy::OpenAddressing => Box::new(OpenAddressing::new(threads)),
+            Strategy::Segmented => Box::new(Segmented::default()),
+            Strategy::Streaming => Box::new(Streaming::new(threads)),
+        }
+    }
+}
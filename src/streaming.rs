@@ -0,0 +1,147 @@
+use std::io::{self, Read};
+use std::sync::Mutex;
+use std::sync::mpsc::{Receiver, SyncSender, sync_channel};
+
+use ahash::RandomState;
+use anyhow::Result as Result;
+use bstr::ByteSlice;
+use hashbrown::HashMap;
+
+use crate::{Accumulator, Aggregator, Measurement, StationStats, compression, sort_stations};
+use crate::histogram::Histogram;
+use crate::parse::{NEWLINE, bom_length, records};
+
+type MeasurementsMap<A = Measurement> = HashMap<Box<[u8]>, A, RandomState>;
+
+/// Path that selects standard input instead of a file.
+pub const STDIN: &str = "-";
+
+const BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Read the input sequentially in large blocks and hand newline aligned blocks
+/// to worker threads, so it also works on pipes that cannot be mapped or seeked.
+pub struct Streaming {
+    threads: usize,
+    histograms: bool
+}
+
+impl Streaming {
+    pub fn new(threads: usize) -> Self {
+        Self { threads, histograms: false }
+    }
+
+    /// Also count every station's values in a histogram, for percentiles.
+    pub fn histograms(mut self, histograms: bool) -> Self {
+        self.histograms = histograms;
+        self
+    }
+}
+
+impl Aggregator for Streaming {
+    fn aggregate(&self, file_path: &str) -> Result<StationStats> {
+        let threads = self.threads;
+        let reader: Box<dyn Read> = match file_path {
+            STDIN => compression::decompress(io::stdin().lock())?,
+            _ => compression::open(file_path, threads)?,
+        };
+        match self.histograms {
+            true => stream::<_, (Measurement, Histogram)>(reader, threads),
+            false => stream::<_, Measurement>(reader, threads),
+        }
+    }
+}
+
+/// Aggregate `file_path`, or standard input when it is [`STDIN`], decompressing
+/// gzip and zstd input on the fly.
+pub fn brc(file_path: &str, threads: usize) -> Result<StationStats> {
+    Streaming::new(threads).aggregate(file_path)
+}
+
+pub fn brc_reader<R: Read>(reader: R, threads: usize) -> Result<StationStats> {
+    stream::<_, Measurement>(reader, threads)
+}
+
+fn stream<R: Read, A: Accumulator>(reader: R, threads: usize) -> Result<StationStats> {
+    let (sender, receiver) = sync_channel(2 * threads);
+    let receiver = Mutex::new(receiver);
+
+    let (read, parts) = std::thread::scope(
+        | scope | {
+            let handles: Vec<_> = (0..threads.max(1))
+                .map(| _ | scope.spawn(|| scan_blocks(&receiver)))
+                .collect();
+            let read = read_blocks(reader, sender);
+            let parts: Vec<MeasurementsMap<A>> = handles
+                .into_iter()
+                .map(| handle | handle.join().unwrap())
+                .collect();
+            (read, parts)
+        }
+    );
+    read?;
+
+    let measurements: MeasurementsMap<A> = parts
+        .into_iter()
+        .fold(
+            HashMap::default(),
+            | mut a, b | {
+                for (name, value) in b {
+                    a.entry(name)
+                        .and_modify(| measurement | measurement.merge(&value))
+                        .or_insert(value);
+                }
+                a
+            }
+        );
+
+    sort_stations(measurements)
+}
+
+/// Fill blocks of at least [`BLOCK_SIZE`] bytes and send everything up to their
+/// last newline, carrying the partial line over to the next block.
+fn read_blocks<R: Read>(mut reader: R, sender: SyncSender<Vec<u8>>) -> io::Result<()> {
+    let mut carry = Vec::new();
+    let mut first_block = true;
+    loop {
+        let mut block = Vec::with_capacity(carry.len() + BLOCK_SIZE);
+        block.append(&mut carry);
+        let read = (&mut reader).take(BLOCK_SIZE as u64).read_to_end(&mut block)?;
+        if first_block {
+            block.drain(..bom_length(&block));
+            first_block = false;
+        }
+
+        if read == 0 {
+            if !block.is_empty() {
+                let _ = sender.send(block);
+            }
+            return Ok(());
+        }
+        match block.rfind_byte(NEWLINE) {
+            Some(position) => {
+                carry = block.split_off(position + 1);
+                // Workers only stop early by panicking, which is reported on join.
+                if sender.send(block).is_err() {
+                    return Ok(());
+                }
+            },
+            None => carry = block,
+        }
+    }
+}
+
+fn scan_blocks<A: Accumulator>(receiver: &Mutex<Receiver<Vec<u8>>>) -> MeasurementsMap<A> {
+    let mut measurements: MeasurementsMap<A> = HashMap::default();
+    loop {
+        let block = match receiver.lock().unwrap().recv() {
+            Ok(block) => block,
+            Err(_) => return measurements,
+        };
+        for (name, value) in records(&block) {
+            measurements
+                .entry_ref(name)
+                .and_modify(| measurement | measurement.update(value))
+                .or_insert_with(|| A::new(value));
+        }
+    }
+}
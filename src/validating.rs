@@ -0,0 +1,196 @@
+use std::fs::File;
+
+use ahash::AHashMap as HashMap;
+use anyhow::Result;
+use bstr::ByteSlice;
+use memmap2::MmapOptions;
+use rayon::prelude::*;
+
+use crate::{Aggregator, Measurement, StationStats, sort_stations};
+use crate::error::{MAX_NAME_LENGTH, RecordError, RecordErrorKind};
+use crate::parse::{BYTE_ORDER_MARK, CARRIAGE_RETURN, MINUS, NEWLINE, PERIOD, SEMICOLON, split_at_newlines};
+
+/// How malformed records are treated by [`Validating`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validation {
+    /// Fail on the first malformed record.
+    Strict,
+    /// Skip malformed records and count them.
+    Lenient,
+}
+
+/// Check every record against the challenge rules before aggregating it.
+///
+/// Slower than the other strategies, but reports where the input is broken
+/// instead of silently producing garbage. `\r\n` line endings and a leading
+/// byte order mark are stripped unless [`reject_crlf`](Self::reject_crlf) is set.
+pub struct Validating {
+    validation: Validation,
+    threads: usize,
+    reject_crlf: bool
+}
+
+impl Validating {
+    pub fn new(validation: Validation, threads: usize) -> Self {
+        Self { validation, threads, reject_crlf: false }
+    }
+
+    /// Treat `\r\n` line endings and a byte order mark as malformed records.
+    pub fn reject_crlf(mut self, reject_crlf: bool) -> Self {
+        self.reject_crlf = reject_crlf;
+        self
+    }
+}
+
+impl Aggregator for Validating {
+    fn aggregate(&self, file_path: &str) -> Result<StationStats> {
+        let file: File = File::open(file_path)?;
+        let mmap = unsafe { MmapOptions::new().map(&file)? };
+
+        let mut boundaries = split_at_newlines(&mmap, self.threads);
+        if self.reject_crlf {
+            // Keep the byte order mark in the first line so it gets reported.
+            boundaries[0].0 = 0;
+        }
+
+        let chunks: Vec<Chunk> = boundaries
+            .into_par_iter()
+            .map(| (start, end) | self.scan_chunk(&mmap, start, end))
+            .collect();
+
+        let mut weather_stations: HashMap<&[u8], Measurement> = HashMap::new();
+        let mut lines_before: u64 = 0;
+        let mut rejected: u64 = 0;
+
+        for chunk in chunks {
+            if let Some(mut error) = chunk.first_error {
+                error.line += lines_before;
+                return Err(error.into());
+            }
+            for (name, measurement) in chunk.measurements {
+                weather_stations
+                    .entry(name)
+                    .and_modify(| item | item.merge(&measurement))
+                    .or_insert(measurement);
+            }
+            lines_before += chunk.lines;
+            rejected += chunk.rejected;
+        }
+
+        let mut weather_stations = sort_stations(weather_stations)?;
+        weather_stations.set_rejected(rejected);
+        Ok(weather_stations)
+    }
+}
+
+impl Validating {
+    fn scan_chunk<'a>(&self, buffer: &'a [u8], start: usize, end: usize) -> Chunk<'a> {
+        let mut chunk = Chunk {
+            measurements: HashMap::new(),
+            lines: 0,
+            rejected: 0,
+            first_error: None,
+        };
+
+        let mut line_start = start;
+        while line_start < end {
+            let line_end = match buffer[line_start..end].find_byte(NEWLINE) {
+                Some(position) => line_start + position,
+                None => end
+            };
+            chunk.lines += 1;
+
+            let line = &buffer[line_start..line_end];
+            let record = match self.reject_crlf {
+                true => check_line_ending(line),
+                false => Ok(line.strip_suffix(&[CARRIAGE_RETURN]).unwrap_or(line)),
+            };
+
+            match record.and_then(parse_record) {
+                Ok((name, value)) => {
+                    chunk.measurements
+                        .entry(name)
+                        .and_modify(| item | item.update(value))
+                        .or_insert_with(|| Measurement::new(value));
+                },
+                Err(kind) => match self.validation {
+                    Validation::Lenient => chunk.rejected += 1,
+                    Validation::Strict => {
+                        chunk.first_error = Some(
+                            RecordError { kind, offset: line_start as u64, line: chunk.lines }
+                        );
+                        break;
+                    }
+                }
+            }
+            line_start = line_end + 1;
+        }
+        chunk
+    }
+}
+
+struct Chunk<'a> {
+    measurements: HashMap<&'a [u8], Measurement>,
+    lines: u64,
+    rejected: u64,
+    /// Line numbers are relative to the start of the chunk.
+    first_error: Option<RecordError>,
+}
+
+fn check_line_ending(line: &[u8]) -> Result<&[u8], RecordErrorKind> {
+    if line.starts_with(BYTE_ORDER_MARK) {
+        return Err(RecordErrorKind::ByteOrderMark);
+    }
+    if line.ends_with(&[CARRIAGE_RETURN]) {
+        return Err(RecordErrorKind::CarriageReturn);
+    }
+    Ok(line)
+}
+
+/// Split a line without its newline into station name and temperature in
+/// tenths of a degree, checking it against the challenge rules.
+pub fn parse_record(line: &[u8]) -> Result<(&[u8], i32), RecordErrorKind> {
+    let separator = line.find_byte(SEMICOLON).ok_or(RecordErrorKind::MissingSeparator)?;
+    let (name, value) = (&line[..separator], &line[(separator + 1)..]);
+
+    if name.is_empty() {
+        return Err(RecordErrorKind::EmptyName);
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(RecordErrorKind::NameTooLong(name.len()));
+    }
+    if std::str::from_utf8(name).is_err() {
+        return Err(RecordErrorKind::InvalidUtf8);
+    }
+    Ok((name, parse_temperature(value)?))
+}
+
+/// Parse a temperature of the form `-?d+.d` into tenths of a degree.
+fn parse_temperature(value: &[u8]) -> Result<i32, RecordErrorKind> {
+    let (is_neg, digits) = match value.split_first() {
+        Some((&MINUS, rest)) => (true, rest),
+        _ => (false, value)
+    };
+    let (integer, fraction) = match digits.iter().position(| &byte | byte == PERIOD) {
+        Some(period) => (&digits[..period], &digits[(period + 1)..]),
+        None => return Err(RecordErrorKind::InvalidValue)
+    };
+
+    let all_digits = | part: &[u8] | part.iter().all(u8::is_ascii_digit);
+    if integer.is_empty() || fraction.len() != 1 || !all_digits(integer) || !all_digits(fraction) {
+        return Err(RecordErrorKind::InvalidValue);
+    }
+
+    let mut acc: i32 = 0;
+    for &digit in integer.iter().chain(fraction) {
+        acc = acc.saturating_mul(10).saturating_add((digit - b'0') as i32);
+    }
+    if acc > 999 {
+        return Err(RecordErrorKind::ValueOutOfRange);
+    }
+
+    match is_neg {
+        true => Ok(-acc),
+        false => Ok(acc),
+    }
+}
@@ -0,0 +1,60 @@
+use brc::Strategy;
+use brc::bench::{self, BenchConfig};
+use brc::generate::Generator;
+
+mod common;
+
+#[test]
+fn reports_every_requested_strategy() {
+    let file = common::write_input(b"Bosaso;5.0\nBosaso;20.0\nKunming;-1.5\n");
+    let strategies = [Strategy::MultithreadedRayon, Strategy::ChunkedReading];
+    let config = BenchConfig { warmup: 1, runs: 3, threads: 2 };
+
+    let report = bench::run(file.path().to_str().unwrap(), &strategies, config, None).unwrap();
+    assert_eq!((report.bytes, report.rows, report.stations), (36, 3, 2));
+
+    let names: Vec<_> = report.results.iter().map(| result | result.strategy).collect();
+    assert_eq!(names, ["multithreaded_rayon", "chunked_reading"]);
+    for result in &report.results {
+        assert_eq!(result.samples.len(), 3);
+        assert!(result.min <= result.median && result.median <= result.max);
+    }
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["results"][1]["strategy"], "chunked_reading");
+}
+
+#[test]
+fn requires_a_run() {
+    let file = common::write_input(b"Bosaso;5.0\n");
+    let config = BenchConfig { warmup: 0, runs: 0, threads: 1 };
+    let path = file.path().to_str().unwrap();
+    assert!(bench::run(path, &[Strategy::Prototyping], config, None).is_err());
+}
+
+/// The merge benchmark of the parallel strategies: many threads on a file with
+/// the largest number of stations the generator writes. Too slow for every
+/// test run, run it with
+/// `cargo test --release --test bench high_cardinality -- --ignored --nocapture`
+/// to see the timings, the equivalent command line being
+/// `brc generate 1000000 --unique-stations 10000 -o high.txt` followed by
+/// `brc bench high.txt -t 16 -s multithreaded_rayon,multithreaded_manual,multithreaded_single_map`.
+#[test]
+#[ignore = "benchmark, run with --ignored"]
+fn high_cardinality_merge() {
+    let mut input = Vec::new();
+    Generator::new(1_000_000, 0).unique_stations(10_000).write(&mut input).unwrap();
+    let file = common::write_input(&input);
+    let strategies = [
+        Strategy::MultithreadedRayon,
+        Strategy::MultithreadedManual,
+        Strategy::MultithreadedSingleMap,
+    ];
+    let config = BenchConfig { warmup: 1, runs: 3, threads: 16 };
+
+    let report = bench::run(file.path().to_str().unwrap(), &strategies, config, None).unwrap();
+    assert_eq!((report.rows, report.stations), (1_000_000, 10_000));
+    for result in &report.results {
+        println!("{:<26} median {:.3}s max {:.3}s", result.strategy, result.median, result.max);
+    }
+}
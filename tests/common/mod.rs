@@ -0,0 +1,22 @@
+#![allow(dead_code)]
+
+use std::io::Write;
+
+use brc::{Strategy, StationStats};
+use tempfile::NamedTempFile;
+
+/// Write `contents` to a temporary measurements file.
+pub fn write_input(contents: &[u8]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(contents).unwrap();
+    file.flush().unwrap();
+    file
+}
+
+/// Run `strategy` on `file`, panicking with the strategy name on failure.
+pub fn aggregate(strategy: Strategy, file: &NamedTempFile, threads: usize) -> StationStats {
+    strategy
+        .aggregator(threads)
+        .aggregate(file.path().to_str().unwrap())
+        .unwrap_or_else(| error | panic!("{strategy:?} failed: {error:#}"))
+}
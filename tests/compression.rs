@@ -0,0 +1,117 @@
+use std::io::Write;
+use std::process::Command;
+
+use brc::{Aggregator, Strategy, StationStats};
+use brc::compression::Compression;
+use brc::generate::Generator;
+use brc::streaming::{Streaming, brc_reader};
+use flate2::Compression as Level;
+use flate2::write::GzEncoder;
+
+mod common;
+
+fn plain_input() -> Vec<u8> {
+    let mut input = Vec::new();
+    Generator::new(50_000, 12).write(&mut input).unwrap();
+    input
+}
+
+fn expected(input: &[u8]) -> StationStats {
+    common::aggregate(Strategy::MultithreadedRayon, &common::write_input(input), 1)
+}
+
+fn gzip(input: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Level::fast());
+    encoder.write_all(input).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// One zstd frame per `frame_size` bytes, cutting through records.
+fn zstd_frames(input: &[u8], frame_size: usize) -> Vec<u8> {
+    input
+        .chunks(frame_size)
+        .flat_map(| chunk | zstd::encode_all(chunk, 1).unwrap())
+        .collect()
+}
+
+fn aggregate_file(contents: &[u8], threads: usize) -> StationStats {
+    let file = common::write_input(contents);
+    Streaming::new(threads).aggregate(file.path().to_str().unwrap()).unwrap()
+}
+
+#[test]
+fn detects_formats_by_magic_bytes() {
+    let input = plain_input();
+    assert_eq!(Compression::detect(&gzip(&input)), Some(Compression::Gzip));
+    let zstd = zstd::encode_all(input.as_slice(), 1).unwrap();
+    assert_eq!(Compression::detect(&zstd), Some(Compression::Zstd));
+    assert_eq!(Compression::detect(&input), None);
+    assert_eq!(Compression::detect(b""), None);
+}
+
+#[test]
+fn gzip_files_and_members() {
+    let input = plain_input();
+    let (first, second) = input.split_at(input.len() / 3);
+    let members = [gzip(first), gzip(second)].concat();
+    for contents in [gzip(&input), members] {
+        assert_eq!(aggregate_file(&contents, 2), expected(&input));
+    }
+}
+
+#[test]
+fn zstd_single_and_multiple_frames() {
+    let input = plain_input();
+    for frame_size in [input.len(), 100_000, 4_099, 7] {
+        let contents = zstd_frames(&input, frame_size);
+        for threads in [1, 3] {
+            let stats = aggregate_file(&contents, threads);
+            assert_eq!(stats, expected(&input), "{frame_size} byte frames");
+        }
+    }
+}
+
+#[test]
+fn decompresses_readers() {
+    let input = plain_input();
+    let compressed = gzip(&input);
+    let reader = brc::compression::decompress(compressed.as_slice()).unwrap();
+    assert_eq!(brc_reader(reader, 2).unwrap(), expected(&input));
+}
+
+#[test]
+fn corrupt_input_is_an_error() {
+    let mut contents = zstd_frames(&plain_input(), 10_000);
+    contents.truncate(contents.len() - 10);
+    let file = common::write_input(&contents);
+    assert!(Streaming::new(2).aggregate(file.path().to_str().unwrap()).is_err());
+}
+
+#[test]
+fn cli_only_streams_compressed_input() {
+    let file = common::write_input(&gzip(b"Abha;1.0\nAbha;3.0\nAbha;2.0\nBern;-4.5\n"));
+    let run = | strategy: &[&str] | {
+        Command::new(env!("CARGO_BIN_EXE_brc"))
+            .arg(file.path())
+            .args(strategy)
+            .args(["--columns", "median"])
+            .output()
+            .unwrap()
+    };
+    for strategy in [&[][..], &["--strategy", "streaming"]] {
+        let output = run(strategy);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "{Abha=1.0/2.0/3.0/2.0, Bern=-4.5/-4.5/-4.5/-4.5}\n",
+        );
+    }
+
+    // An explicit strategy is refused rather than silently replaced by streaming.
+    for strategy in ["multithreaded_rayon", "open_addressing"] {
+        let output = run(&["--strategy", strategy]);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("cannot read stdin or compressed input"), "{stderr}");
+    }
+}
@@ -0,0 +1,73 @@
+use std::path::PathBuf;
+
+use brc::{Strategy, StationStats};
+use brc::format::{OutputFormat, write_files, write_stats};
+use brc::multi_file::FileStats;
+
+mod common;
+
+fn stats(input: &[u8]) -> StationStats {
+    common::aggregate(Strategy::MultithreadedRayon, &common::write_input(input), 1)
+}
+
+fn render(input: &[u8], format: OutputFormat, precision: usize) -> String {
+    let mut output = Vec::new();
+    write_stats(&stats(input), format, precision, &[], &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn csv_quotes_names_like_rfc_4180() {
+    let input = b"Washington, D.C.;1.0\nSay \"cheese\";-2.5\nPlain;3.0\nTab\tbed;4.0\n";
+    assert_eq!(
+        render(input, OutputFormat::Csv, 1),
+        "station,min,mean,max,count,sum\n\
+         Plain,3.0,3.0,3.0,1,3.0\n\
+         \"Say \"\"cheese\"\"\",-2.5,-2.5,-2.5,1,-2.5\n\
+         Tab\tbed,4.0,4.0,4.0,1,4.0\n\
+         \"Washington, D.C.\",1.0,1.0,1.0,1,1.0\n",
+    );
+}
+
+#[test]
+fn tsv_quotes_names_with_tabs() {
+    let input = b"Washington, D.C.;1.0\nTab\tbed;4.0\n";
+    assert_eq!(
+        render(input, OutputFormat::Tsv, 1),
+        "station\tmin\tmean\tmax\tcount\tsum\n\
+         \"Tab\tbed\"\t4.0\t4.0\t4.0\t1\t4.0\n\
+         Washington, D.C.\t1.0\t1.0\t1.0\t1\t1.0\n",
+    );
+}
+
+#[test]
+fn precision_rounds_half_up() {
+    let input = b"Abha;-0.1\nAbha;-0.2\nAbha;1.0\n";
+    let row = | precision | {
+        render(input, OutputFormat::Csv, precision).lines().nth(1).unwrap().to_owned()
+    };
+    assert_eq!(row(0), "Abha,0,0,1,3,1");
+    assert_eq!(row(1), "Abha,-0.2,0.2,1.0,3,0.7");
+    assert_eq!(row(3), "Abha,-0.200,0.233,1.000,3,0.700");
+    let halves = render(b"Abha;-2.5\nAbha;-2.4\n", OutputFormat::Csv, 0);
+    assert_eq!(halves.lines().nth(1), Some("Abha,-2,-2,-2,2,-5"));
+}
+
+#[test]
+fn header_only_for_empty_input() {
+    assert_eq!(render(b"", OutputFormat::Csv, 1), "station,min,mean,max,count,sum\n");
+}
+
+#[test]
+fn per_file_rows_have_a_file_column() {
+    let total = stats(b"Abha;1.0\n");
+    let files = [FileStats { path: PathBuf::from("day,1.csv"), stats: total.clone() }];
+    let mut output = Vec::new();
+    write_files(&files, &total, OutputFormat::Csv, 2, &[], &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "file,station,min,mean,max,count,sum\n\
+         \"day,1.csv\",Abha,1.00,1.00,1.00,1,1.00\n\
+         ,Abha,1.00,1.00,1.00,1,1.00\n",
+    );
+}
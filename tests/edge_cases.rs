@@ -0,0 +1,134 @@
+use brc::{Aggregator, Strategy, StationStats};
+use brc::error::NoData;
+use brc::format::write_output;
+use brc::validating::{Validating, Validation};
+use clap::ValueEnum;
+
+mod common;
+
+/// Thread counts around and far above the number of lines in the small inputs.
+const THREAD_COUNTS: [usize; 5] = [1, 2, 3, 8, 64];
+
+fn assert_all_strategies(input: &[u8], check: impl Fn(&StationStats)) {
+    let file = common::write_input(input);
+    for &strategy in Strategy::value_variants() {
+        for threads in THREAD_COUNTS {
+            let stats = common::aggregate(strategy, &file, threads);
+            check(&stats);
+        }
+    }
+}
+
+#[test]
+fn empty_file() {
+    assert_all_strategies(b"", | stats | assert!(stats.is_empty()));
+}
+
+#[test]
+fn empty_file_renders_empty_braces() {
+    assert_all_strategies(
+        b"",
+        | stats | {
+            let mut output = Vec::new();
+            write_output(stats, &mut output).unwrap();
+            assert_eq!(output, b"{}\n");
+            assert_eq!(stats.clone().ensure_not_empty(), Err(NoData));
+        }
+    );
+}
+
+#[test]
+fn all_lines_rejected_is_no_data() {
+    let file = common::write_input(b"Bosaso\nKunming;hot\n");
+    let stats = Validating::new(Validation::Lenient, 2)
+        .aggregate(file.path().to_str().unwrap())
+        .unwrap();
+    assert_eq!(stats.rejected(), 2);
+    assert_eq!(stats.ensure_not_empty(), Err(NoData));
+}
+
+#[test]
+fn single_line() {
+    assert_all_strategies(
+        b"Kunming;19.8\n",
+        | stats | {
+            assert_eq!(stats.len(), 1);
+            assert_eq!(stats.get("Kunming").unwrap().max(), 19.8);
+        }
+    );
+}
+
+#[test]
+fn single_line_without_newline() {
+    assert_all_strategies(
+        b"Kunming;-19.8",
+        | stats | {
+            assert_eq!(stats.len(), 1);
+            assert_eq!(stats.get("Kunming").unwrap().min(), -19.8);
+        }
+    );
+}
+
+#[test]
+fn fewer_lines_than_threads() {
+    assert_all_strategies(
+        b"Bosaso;5.0\nBosaso;20.0\nPetropavlovsk-Kamchatsky;9.5\n",
+        | stats | {
+            assert_eq!(stats.len(), 2);
+            assert_eq!(stats.get("Bosaso").unwrap().count(), 2);
+            assert_eq!(stats.get("Petropavlovsk-Kamchatsky").unwrap().count(), 1);
+        }
+    );
+}
+
+#[test]
+fn missing_final_newline() {
+    assert_all_strategies(
+        b"Bosaso;5.0\nBosaso;20.0\nPetropavlovsk-Kamchatsky;9.5",
+        | stats | {
+            assert_eq!(stats.get("Bosaso").unwrap().count(), 2);
+            assert_eq!(stats.get("Petropavlovsk-Kamchatsky").unwrap().sum(), 9.5);
+        }
+    );
+}
+
+#[test]
+fn missing_final_newline_after_several_buffers() {
+    let mut input = "Bosaso;5.0\n".repeat(300_000);
+    input.push_str("Kunming;19.8");
+
+    assert_all_strategies(
+        input.as_bytes(),
+        | stats | {
+            assert_eq!(stats.get("Bosaso").unwrap().count(), 300_000);
+            assert_eq!(stats.get("Kunming").unwrap().count(), 1);
+        }
+    );
+}
+
+#[test]
+fn lines_without_separator_are_skipped() {
+    for input in [
+        &b"Bosaso;5.0\n\nno separator\nKunming;19.8\n\n"[..],
+        b"\nBosaso;5.0\nno separator\r\nKunming;19.8\nno separator",
+    ] {
+        assert_all_strategies(
+            input,
+            | stats | {
+                let names: Vec<&str> = stats.iter().map(| station | station.name()).collect();
+                assert_eq!(names, ["Bosaso", "Kunming"]);
+                assert_eq!(stats.get("Kunming").unwrap().count(), 1);
+                assert_eq!(stats.get("Bosaso").unwrap().max(), 5.0);
+            }
+        );
+    }
+}
+
+#[test]
+fn zero_threads_use_one() {
+    let file = common::write_input(b"Bosaso;5.0\nKunming;19.8\n");
+    for &strategy in Strategy::value_variants() {
+        let stats = common::aggregate(strategy, &file, 0);
+        assert_eq!(stats.len(), 2, "{strategy:?}");
+    }
+}
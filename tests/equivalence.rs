@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+
+use brc::{Measurement, Strategy, StationStats};
+use brc::generate::Generator;
+use clap::ValueEnum;
+
+mod common;
+
+const THREAD_COUNTS: [usize; 5] = [1, 2, 3, 7, 16];
+
+/// Read buffer size of the strategies that stream the file.
+const BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Names that stress hashing and comparison: shared prefixes, multi-byte
+/// characters and the 1 and 100 byte extremes.
+const NAMES: &[&str] = &[
+    "A",
+    "Ab",
+    "Abha",
+    "Abhab",
+    "São Paulo",
+    "Zürich",
+    "İzmir",
+    "Petropavlovsk-Kamchatsky",
+    "Washington, D.C.",
+    "北京",
+    "ד",
+    "Llanfairpwllgwyngyllgogerychwyrndrobwllllantysiliogogogoch",
+];
+
+/// Tiny xorshift generator, the suite only needs reproducible noise.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+}
+
+/// Straightforward implementation every strategy has to agree with.
+fn reference(input: &[u8]) -> BTreeMap<String, Measurement> {
+    let mut stations = BTreeMap::new();
+    for line in std::str::from_utf8(input).unwrap().lines() {
+        let (name, value) = line.split_once(';').unwrap();
+        let tenths = (value.parse::<f64>().unwrap() * 10.).round() as i32;
+        stations
+            .entry(name.to_owned())
+            .and_modify(| measurement: &mut Measurement | measurement.update(tenths))
+            .or_insert_with(|| Measurement::new(tenths));
+    }
+    stations
+}
+
+fn as_map(stats: &StationStats) -> BTreeMap<String, Measurement> {
+    stats
+        .iter()
+        .map(| station | (station.name().to_owned(), *station.measurement()))
+        .collect()
+}
+
+fn assert_equivalent(input: &[u8], thread_counts: &[usize]) {
+    let expected = reference(input);
+    let file = common::write_input(input);
+    for &strategy in Strategy::value_variants() {
+        for &threads in thread_counts {
+            let stats = common::aggregate(strategy, &file, threads);
+            assert!(
+                as_map(&stats) == expected,
+                "{strategy:?} with {threads} threads differs on {} bytes", input.len(),
+            );
+        }
+    }
+}
+
+fn random_name(rng: &mut Rng) -> String {
+    match rng.below(8) {
+        0 => "x".repeat(1 + rng.below(100) as usize),
+        1 => "é".repeat(1 + rng.below(50) as usize),
+        _ => NAMES[rng.below(NAMES.len() as u64) as usize].to_owned(),
+    }
+}
+
+fn random_value(rng: &mut Rng) -> String {
+    let tenths = rng.below(1999) as i32 - 999;
+    let sign = if tenths < 0 { "-" } else { "" };
+    format!("{sign}{}.{}", tenths.abs() / 10, tenths.abs() % 10)
+}
+
+fn random_input(rng: &mut Rng, rows: usize) -> Vec<u8> {
+    let mut input = String::new();
+    for _ in 0..rows {
+        input.push_str(&random_name(rng));
+        input.push(';');
+        input.push_str(&random_value(rng));
+        input.push('\n');
+    }
+    input.into_bytes()
+}
+
+#[test]
+fn random_small_inputs() {
+    let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+    for rows in (0..40).chain([97, 256, 1000]) {
+        let mut input = random_input(&mut rng, rows);
+        assert_equivalent(&input, &THREAD_COUNTS);
+        if input.pop().is_some() {
+            assert_equivalent(&input, &THREAD_COUNTS);
+        }
+    }
+}
+
+#[test]
+fn extreme_values() {
+    let input = b"A;-99.9\nA;99.9\nA;0.0\nA;-0.0\nB;-0.1\nB;0.1\nC;-9.9\nC;9.9\nC;-10.0\nC;10.0\n";
+    assert_equivalent(input, &THREAD_COUNTS);
+}
+
+#[test]
+fn longest_names() {
+    let long = "x".repeat(100);
+    let multi_byte = "é".repeat(50);
+    let input = format!("{long};1.0\n{multi_byte};-2.0\n{long};-3.5\n{multi_byte};4.5\n");
+    assert_equivalent(input.as_bytes(), &THREAD_COUNTS);
+}
+
+#[test]
+fn records_straddling_buffer_boundaries() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    let record = format!("{};-12.3\n", "y".repeat(100));
+    for offset in [0, 1, 50, 100, 101, 105, record.len() - 1] {
+        // Random records, then short padding records so the long one starts
+        // exactly `offset` bytes before the end of the first buffer.
+        let start = BUFFER_SIZE - offset;
+        let mut input = Vec::with_capacity(BUFFER_SIZE + record.len());
+        while input.len() + 200 < start {
+            input.extend_from_slice(&random_input(&mut rng, 1));
+        }
+        while start - input.len() > 11 {
+            input.extend_from_slice(b"P;1.0\n");
+        }
+        input.extend_from_slice(&vec![b'P'; start - input.len() - 5]);
+        input.extend_from_slice(b";1.0\n");
+        assert_eq!(input.len(), start);
+        input.extend_from_slice(record.as_bytes());
+        input.extend_from_slice(&random_input(&mut rng, 100));
+        assert_equivalent(&input, &[1, 2]);
+    }
+}
+
+#[test]
+fn generated_files() {
+    for seed in 0..3 {
+        let mut input = Vec::new();
+        Generator::new(150_000, seed).write(&mut input).unwrap();
+        assert_equivalent(&input, &[1, 4]);
+
+        let mut input = Vec::new();
+        Generator::new(150_000, seed).unique_stations(10_000).write(&mut input).unwrap();
+        assert_equivalent(&input, &[3]);
+    }
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "arrow")]
+
+use std::fs::File;
+
+use arrow_array::{Float64Array, RecordBatch, StringArray, UInt64Array};
+use arrow_ipc::reader::FileReader;
+use brc::Strategy;
+use brc::export::{schema, write_arrow, write_parquet};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+mod common;
+
+fn stats() -> brc::StationStats {
+    let input = b"Kunming;19.8\nBosaso;-5.0\nKunming;-1.2\nS\xC3\xA3o Paulo;0.1\nBosaso;5.5\n";
+    common::aggregate(Strategy::MultithreadedRayon, &common::write_input(input), 1)
+}
+
+fn assert_table(batch: &RecordBatch) {
+    assert_eq!(batch.schema().as_ref(), &schema());
+    let column = | name: &str | batch.column_by_name(name).unwrap().clone();
+
+    let names = column("name");
+    let names = names.as_any().downcast_ref::<StringArray>().unwrap();
+    assert_eq!(names.iter().flatten().collect::<Vec<_>>(), ["Bosaso", "Kunming", "São Paulo"]);
+
+    let values = | name: &str | -> Vec<f64> {
+        let array = column(name);
+        array.as_any().downcast_ref::<Float64Array>().unwrap().values().to_vec()
+    };
+    assert_eq!(values("min"), [-5.0, -1.2, 0.1]);
+    // Bosaso's mean of 0.25 is exported as is, the text output rounds it to 0.3.
+    assert_eq!(values("mean"), [0.25, 9.3, 0.1]);
+    assert_eq!(values("max"), [5.5, 19.8, 0.1]);
+    assert_eq!(values("sum"), [0.5, 18.6, 0.1]);
+
+    let counts = column("count");
+    let counts = counts.as_any().downcast_ref::<UInt64Array>().unwrap();
+    assert_eq!(counts.values().to_vec(), [2, 2, 1]);
+}
+
+#[test]
+fn arrow_ipc_round_trip() {
+    let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+    write_arrow(&stats(), &path).unwrap();
+
+    let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+    let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+    assert_eq!(batches.len(), 1);
+    assert_table(&batches[0]);
+}
+
+#[test]
+fn parquet_round_trip() {
+    let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+    write_parquet(&stats(), &path).unwrap();
+
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+        .unwrap()
+        .build()
+        .unwrap();
+    let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+    assert_eq!(batches.len(), 1);
+    assert_table(&batches[0]);
+}
+
+#[test]
+fn empty_stations_export_an_empty_table() {
+    let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+    write_parquet(&Default::default(), &path).unwrap();
+    let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+    assert_eq!(builder.metadata().file_metadata().num_rows(), 0);
+}
@@ -0,0 +1,77 @@
+use brc::Aggregator;
+use brc::generate::Generator;
+use brc::validating::{Validating, Validation};
+
+mod common;
+
+fn generate(generator: Generator) -> Vec<u8> {
+    let mut output = Vec::new();
+    generator.write(&mut output).unwrap();
+    output
+}
+
+#[test]
+fn same_seed_produces_same_file() {
+    assert_eq!(generate(Generator::new(200_000, 7)), generate(Generator::new(200_000, 7)));
+    assert_ne!(generate(Generator::new(1_000, 7)), generate(Generator::new(1_000, 8)));
+}
+
+#[test]
+fn blocks_do_not_repeat() {
+    // The generator renders blocks of 65536 rows, each from its own random state.
+    const BLOCK_ROWS: usize = 65_536;
+    let output = generate(Generator::new(4 * BLOCK_ROWS as u64, 1));
+    let lines: Vec<&[u8]> = output.split(| &byte | byte == b'\n').collect();
+    let block = | index: usize | &lines[(index * BLOCK_ROWS)..((index + 1) * BLOCK_ROWS)];
+
+    for other in 1..4 {
+        for shift in 0..4 {
+            let equal = block(0)[shift..]
+                .iter()
+                .zip(block(other))
+                .filter(| (first, second) | first == second)
+                .count();
+            assert!(equal < BLOCK_ROWS / 100, "block {other} repeats block 0 shifted by {shift}");
+        }
+    }
+}
+
+#[test]
+fn output_is_independent_of_thread_count() {
+    let generate_on = | threads | {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap()
+            .install(|| generate(Generator::new(300_000, 11)))
+    };
+    assert_eq!(generate_on(1), generate_on(5));
+}
+
+#[test]
+fn writes_requested_rows_of_valid_records() {
+    for rows in [0, 1, 65_535, 65_536, 100_001] {
+        let output = generate(Generator::new(rows, 1));
+        let file = common::write_input(&output);
+        let stats = Validating::new(Validation::Strict, 4)
+            .aggregate(file.path().to_str().unwrap())
+            .unwrap();
+        assert_eq!(stats.iter().map(| station | station.count()).sum::<u64>(), rows);
+    }
+}
+
+#[test]
+fn unique_stations_are_all_used() {
+    let output = generate(Generator::new(500_000, 3).unique_stations(10_000));
+    let file = common::write_input(&output);
+    let stats = Validating::new(Validation::Strict, 4)
+        .aggregate(file.path().to_str().unwrap())
+        .unwrap();
+    assert_eq!(stats.len(), 10_000);
+}
+
+#[test]
+fn rejects_too_many_unique_stations() {
+    let mut output = Vec::new();
+    assert!(Generator::new(10, 3).unique_stations(10_001).write(&mut output).is_err());
+}
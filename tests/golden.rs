@@ -0,0 +1,34 @@
+use std::fs;
+use std::path::Path;
+
+use brc::Strategy;
+use brc::format::write_output;
+use clap::ValueEnum;
+
+/// Run every strategy on each `tests/data/*.txt` file and compare the rendered
+/// output with the matching `.out` file, in the format of the 1BRC test suite.
+#[test]
+fn output_matches_reference() {
+    let data = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data");
+    let mut inputs: Vec<_> = fs::read_dir(&data)
+        .unwrap()
+        .map(| entry | entry.unwrap().path())
+        .filter(| path | path.extension().is_some_and(| ext | ext == "txt"))
+        .collect();
+    inputs.sort();
+    assert!(!inputs.is_empty());
+
+    for input in inputs {
+        let expected = fs::read_to_string(input.with_extension("out")).unwrap();
+        for &strategy in Strategy::value_variants() {
+            let stats = strategy
+                .aggregator(2)
+                .aggregate(input.to_str().unwrap())
+                .unwrap_or_else(| error | panic!("{strategy:?} on {input:?}: {error:#}"));
+
+            let mut output = Vec::new();
+            write_output(&stats, &mut output).unwrap();
+            assert_eq!(String::from_utf8(output).unwrap(), expected, "{strategy:?} on {input:?}");
+        }
+    }
+}
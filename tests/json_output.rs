@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use brc::Strategy;
+use brc::format::{OutputFormat, write_files, write_stats};
+use brc::multi_file::FileStats;
+use serde_json::{Value, json};
+
+mod common;
+
+const INPUT: &[u8] = b"Quote\"d;1.0\nBack\\slash;-2.5\nBell\x07;3.3\nQuote\"d;2.0\nNew\rline;-0.1\n";
+
+fn render(format: OutputFormat) -> String {
+    let stats = common::aggregate(Strategy::MultithreadedRayon, &common::write_input(INPUT), 1);
+    let mut output = Vec::new();
+    write_stats(&stats, format, 1, &[], &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn json_object_keyed_by_station() {
+    let output = render(OutputFormat::Json);
+    let value: Value = serde_json::from_str(&output).unwrap();
+    let expected = json!({"min": 1.0, "mean": 1.5, "max": 2.0, "count": 2, "sum": 3.0});
+    assert_eq!(value["Quote\"d"], expected);
+    assert_eq!(value["Back\\slash"]["min"], json!(-2.5));
+    assert_eq!(value["Bell\u{7}"]["count"], json!(1));
+    assert_eq!(value["New\rline"]["mean"], json!(-0.1));
+    assert!(output.contains(r#""Bell\u0007""#), "{output}");
+}
+
+#[test]
+fn json_lines_has_one_station_per_line() {
+    let output = render(OutputFormat::Jsonl);
+    let lines: Vec<Value> = output
+        .lines()
+        .map(| line | serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0]["station"], json!("Back\\slash"));
+    assert_eq!(lines[3]["station"], json!("Quote\"d"));
+    assert_eq!(lines[3]["sum"], json!(3.0));
+}
+
+#[test]
+fn empty_input() {
+    assert_eq!(render_empty(OutputFormat::Json), "{}\n");
+    assert_eq!(render_empty(OutputFormat::Jsonl), "");
+}
+
+fn render_empty(format: OutputFormat) -> String {
+    let mut output = Vec::new();
+    write_stats(&Default::default(), format, 1, &[], &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn per_file_json() {
+    let file = common::write_input(b"Abha;1.0\n");
+    let stats = common::aggregate(Strategy::MultithreadedRayon, &file, 1);
+    let files = [FileStats { path: PathBuf::from("day \"1\".txt"), stats: stats.clone() }];
+
+    let mut output = Vec::new();
+    write_files(&files, &stats, OutputFormat::Json, 1, &[], &mut output).unwrap();
+    let value: Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(value["files"]["day \"1\".txt"]["Abha"]["max"], json!(1.0));
+    assert_eq!(value["total"]["Abha"]["count"], json!(1));
+
+    let mut output = Vec::new();
+    write_files(&files, &stats, OutputFormat::Jsonl, 1, &[], &mut output).unwrap();
+    let lines: Vec<Value> = output
+        .split(| &byte | byte == b'\n')
+        .filter(| line | !line.is_empty())
+        .map(| line | serde_json::from_slice(line).unwrap())
+        .collect();
+    assert_eq!(lines[0]["file"], json!("day \"1\".txt"));
+    assert_eq!(lines[1].get("file"), None);
+}
@@ -0,0 +1,53 @@
+use brc::{Aggregator, Strategy};
+use brc::error::{RecordError, RecordErrorKind};
+use brc::validating::{Validating, Validation};
+use clap::ValueEnum;
+
+mod common;
+
+const UNIX: &[u8] = b"Bosaso;5.0\nBosaso;20.0\nZ\xc3\xbcrich;-3.9\nZ\xc3\xbcrich;4.0\n";
+const WINDOWS: &[u8] = b"\xef\xbb\xbfBosaso;5.0\r\nBosaso;20.0\r\nZ\xc3\xbcrich;-3.9\r\nZ\xc3\xbcrich;4.0\r\n";
+const WINDOWS_UNTERMINATED: &[u8] = b"\xef\xbb\xbfBosaso;5.0\r\nBosaso;20.0\r\nZ\xc3\xbcrich;-3.9\r\nZ\xc3\xbcrich;4.0";
+
+#[test]
+fn crlf_and_bom_are_stripped_by_every_strategy() {
+    let unix = common::write_input(UNIX);
+    let expected = common::aggregate(Strategy::FirstAttempt, &unix, 1);
+
+    for input in [WINDOWS, WINDOWS_UNTERMINATED] {
+        let windows = common::write_input(input);
+        for &strategy in Strategy::value_variants() {
+            for threads in [1, 3] {
+                assert_eq!(common::aggregate(strategy, &windows, threads), expected, "{strategy:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn validation_strips_crlf_and_bom_by_default() {
+    let unix = common::write_input(UNIX);
+    let windows = common::write_input(WINDOWS);
+
+    for validation in [Validation::Strict, Validation::Lenient] {
+        let aggregator = Validating::new(validation, 2);
+        let expected = aggregator.aggregate(unix.path().to_str().unwrap()).unwrap();
+        let stats = aggregator.aggregate(windows.path().to_str().unwrap()).unwrap();
+        assert_eq!(stats, expected);
+    }
+}
+
+#[test]
+fn strict_validation_can_reject_crlf_and_bom() {
+    let aggregator = Validating::new(Validation::Strict, 2).reject_crlf(true);
+
+    let windows = common::write_input(WINDOWS);
+    let error = aggregator.aggregate(windows.path().to_str().unwrap()).unwrap_err();
+    let expected = RecordError { kind: RecordErrorKind::ByteOrderMark, offset: 0, line: 1 };
+    assert_eq!(error.downcast_ref::<RecordError>(), Some(&expected));
+
+    let without_bom = common::write_input(&WINDOWS[3..]);
+    let error = aggregator.aggregate(without_bom.path().to_str().unwrap()).unwrap_err();
+    let expected = RecordError { kind: RecordErrorKind::CarriageReturn, offset: 0, line: 1 };
+    assert_eq!(error.downcast_ref::<RecordError>(), Some(&expected));
+}
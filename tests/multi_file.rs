@@ -0,0 +1,100 @@
+use std::fs;
+use std::path::PathBuf;
+
+use brc::Strategy;
+use brc::generate::Generator;
+use brc::multi_file::{aggregate_files, expand_inputs};
+
+mod common;
+
+fn generated(rows: u64, seed: u64) -> Vec<u8> {
+    let mut output = Vec::new();
+    Generator::new(rows, seed).write(&mut output).unwrap();
+    output
+}
+
+#[test]
+fn combined_files_match_their_concatenation() {
+    let directory = tempfile::tempdir().unwrap();
+    let days = [generated(30_000, 1), generated(1, 2), Vec::new(), generated(12_345, 3)];
+    let mut paths = Vec::new();
+    for (day, contents) in days.iter().enumerate() {
+        let path = directory.path().join(format!("day-{day}.txt"));
+        fs::write(&path, contents).unwrap();
+        paths.push(path);
+    }
+
+    let aggregate = | contents: &[u8] | {
+        common::aggregate(Strategy::MultithreadedRayon, &common::write_input(contents), 1)
+    };
+    let expected = aggregate(&days.concat());
+    for threads in [1, 2, 7] {
+        let report = aggregate_files(&paths, threads).unwrap();
+        assert_eq!(report.total, expected);
+        for (file, contents) in report.files.iter().zip(&days) {
+            assert_eq!(file.stats, aggregate(contents), "{}", file.path.display());
+        }
+    }
+}
+
+#[test]
+fn blank_lines_and_missing_final_newline() {
+    let directory = tempfile::tempdir().unwrap();
+    let first = directory.path().join("first.txt");
+    let second = directory.path().join("second.txt");
+    fs::write(&first, b"Abha;1.0\n\nAbha;3.0\n\n").unwrap();
+    fs::write(&second, b"\nBosaso;5.0\nAbha;-2.0").unwrap();
+
+    for threads in [1, 2, 8] {
+        let report = aggregate_files(&[first.clone(), second.clone()], threads).unwrap();
+        let abha = report.total.get("Abha").unwrap();
+        assert_eq!((abha.count(), abha.min(), abha.max()), (3, -2.0, 3.0));
+        assert_eq!(report.total.get("Bosaso").unwrap().count(), 1);
+        assert_eq!(report.total.len(), 2);
+    }
+}
+
+#[test]
+fn compressed_and_plain_files_mix() {
+    let directory = tempfile::tempdir().unwrap();
+    let (plain, compressed) = (generated(20_000, 4), generated(20_000, 5));
+    let plain_path = directory.path().join("plain.txt");
+    let compressed_path = directory.path().join("compressed.zst");
+    fs::write(&plain_path, &plain).unwrap();
+    fs::write(&compressed_path, zstd::encode_all(compressed.as_slice(), 1).unwrap()).unwrap();
+
+    let report = aggregate_files(&[plain_path, compressed_path], 3).unwrap();
+    let expected = common::aggregate(
+        Strategy::MultithreadedRayon, &common::write_input(&[plain, compressed].concat()), 1,
+    );
+    assert_eq!(report.total, expected);
+}
+
+#[test]
+fn expands_directories_and_globs_in_order() {
+    let directory = tempfile::tempdir().unwrap();
+    for name in ["b.txt", "a.txt", "c.csv"] {
+        fs::write(directory.path().join(name), b"Abha;1.0\n").unwrap();
+    }
+    fs::create_dir(directory.path().join("nested")).unwrap();
+    let root = directory.path().to_str().unwrap().to_owned();
+
+    let names = | paths: Vec<PathBuf> | -> Vec<String> {
+        paths
+            .iter()
+            .map(| path | path.file_name().unwrap().to_str().unwrap().to_owned())
+            .collect()
+    };
+    assert_eq!(names(expand_inputs(std::slice::from_ref(&root)).unwrap()), ["a.txt", "b.txt", "c.csv"]);
+    assert_eq!(names(expand_inputs(&[format!("{root}/*.txt")]).unwrap()), ["a.txt", "b.txt"]);
+    assert_eq!(names(expand_inputs(&["missing.txt".to_owned()]).unwrap()), ["missing.txt"]);
+    assert!(expand_inputs(&[format!("{root}/*.json")]).is_err());
+    let error = expand_inputs(&[format!("{root}/nested")]).unwrap_err();
+    assert!(error.to_string().contains("no files in directory"), "{error}");
+}
+
+#[test]
+fn missing_file_names_the_path() {
+    let error = aggregate_files(&[PathBuf::from("/nonexistent/day.txt")], 2).unwrap_err();
+    assert!(format!("{error:#}").contains("failed to process /nonexistent/day.txt"), "{error:#}");
+}
@@ -0,0 +1,40 @@
+use brc::Aggregator;
+use brc::open_addressing::{MAX_STATIONS, OpenAddressing};
+
+mod common;
+
+fn aggregate(input: &[u8], threads: usize) -> anyhow::Result<brc::StationStats> {
+    let file = common::write_input(input);
+    OpenAddressing::new(threads).aggregate(file.path().to_str().unwrap())
+}
+
+#[test]
+fn long_names_sharing_the_inline_prefix_stay_apart() {
+    let prefix = "Petropavlovsk-Ka";
+    let input = format!(
+        "{prefix}mchatsky;1.0\n{prefix}mchatskx;2.0\n{prefix};3.0\n{prefix}mchatsky;-1.0\n"
+    );
+    for threads in [1, 2, 4] {
+        let stats = aggregate(input.as_bytes(), threads).unwrap();
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats.get("Petropavlovsk-Kamchatsky").unwrap().count(), 2);
+        assert_eq!(stats.get(prefix).unwrap().max(), 3.0);
+    }
+}
+
+#[test]
+fn names_of_every_word_length() {
+    let input: String = (1..=100)
+        .map(| length | format!("{};-{}.5\n", "z".repeat(length), length % 100))
+        .collect();
+    let stats = aggregate(input.as_bytes(), 3).unwrap();
+    assert_eq!(stats.len(), 100);
+    assert_eq!(stats.get("zzzzzzzz").unwrap().min(), -8.5);
+}
+
+#[test]
+fn too_many_stations_is_an_error() {
+    let input: String = (0..=MAX_STATIONS).map(| index | format!("S{index};1.0\n")).collect();
+    let error = aggregate(input.as_bytes(), 1).unwrap_err();
+    assert!(error.to_string().contains("distinct stations"), "{error}");
+}
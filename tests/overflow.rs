@@ -0,0 +1,22 @@
+use brc::Strategy;
+use clap::ValueEnum;
+
+mod common;
+
+/// Enough rows at the maximum temperature to push the sum past `i32::MAX` tenths.
+const ROWS: usize = (i32::MAX as usize / 999) + 1_000;
+
+#[test]
+fn sum_does_not_overflow_on_hot_station() {
+    let input = "Hot;99.9\n".repeat(ROWS);
+    let file = common::write_input(input.as_bytes());
+
+    for &strategy in Strategy::value_variants() {
+        let stats = common::aggregate(strategy, &file, 4);
+        let station = stats.get("Hot").unwrap();
+        let measurement = station.measurement();
+        assert!(measurement.sum > i32::MAX as i64, "{strategy:?} overflowed");
+        assert_eq!(measurement.sum, 999 * measurement.count as i64, "{strategy:?}");
+        assert_eq!(station.mean(), 99.9, "{strategy:?}");
+    }
+}
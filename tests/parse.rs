@@ -0,0 +1,50 @@
+use brc::parse::{parse_ascii_to_int, parse_temperature, records};
+
+/// Every temperature the format allows, including the `-0.0` spelling.
+fn all_temperatures() -> impl Iterator<Item = String> {
+    (-999..=999)
+        .map(| tenths: i32 | {
+            let sign = if tenths < 0 { "-" } else { "" };
+            format!("{sign}{}.{}", tenths.abs() / 10, tenths.abs() % 10)
+        })
+        .chain(["-0.0".to_owned()])
+}
+
+#[test]
+fn swar_matches_scalar_on_every_value() {
+    let followers: [&[u8]; 6] = [b"", b"\n", b"\r\n", b"\nAbha;1.0\n", b"\n\xff\xff\xff\xff\xff\xff\xff", b"\n;;;;;;;;"];
+    for temperature in all_temperatures() {
+        let expected = parse_ascii_to_int(temperature.as_bytes());
+        for follower in followers {
+            let buffer = [temperature.as_bytes(), follower].concat();
+            assert_eq!(
+                parse_temperature(&buffer),
+                (expected, temperature.len()),
+                "{temperature:?} followed by {follower:?}",
+            );
+        }
+    }
+}
+
+#[test]
+fn swar_matches_scalar_with_random_trailing_bytes() {
+    let mut state: u64 = 0x853C_49E6_748F_EA9B;
+    for temperature in all_temperatures() {
+        let expected = parse_ascii_to_int(temperature.as_bytes());
+        for _ in 0..16 {
+            state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            let buffer = [temperature.as_bytes(), b"\n", &state.to_le_bytes()].concat();
+            assert_eq!(parse_temperature(&buffer), (expected, temperature.len()), "{buffer:?}");
+        }
+    }
+}
+
+#[test]
+fn records_handle_line_endings_and_tails() {
+    let buffer = b"Abha;-1.5\nBosaso;22.0\r\nKunming;9.9";
+    let parsed: Vec<_> = records(buffer).collect();
+    assert_eq!(parsed, [(&b"Abha"[..], -15), (b"Bosaso", 220), (b"Kunming", 99)]);
+
+    assert_eq!(records(b"Abha;1.0\nno separator").count(), 1);
+    assert_eq!(records(b"").count(), 0);
+}
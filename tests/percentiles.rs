@@ -0,0 +1,145 @@
+use std::process::Command;
+
+use brc::Aggregator;
+use brc::format::{Column, OutputFormat, write_stats};
+use brc::generate::Generator;
+use brc::histogram::Histogram;
+use brc::multithreaded_rayon::MultithreadedRayon;
+use brc::streaming::Streaming;
+
+mod common;
+
+/// Nearest-rank percentile of `values` by its definition in exact integers: the
+/// smallest value that at least `per_mille` thousandths of the values are less
+/// than or equal to.
+fn brute_force_percentile(values: &[i32], per_mille: u64) -> i32 {
+    let total = values.len() as u64;
+    (-999..=999)
+        .find(
+            | &candidate | {
+                let below = values.iter().filter(| &&value | value <= candidate).count() as u64;
+                below > 0 && 1000 * below >= per_mille * total
+            }
+        )
+        .unwrap()
+}
+
+fn histogram(values: &[i32]) -> Histogram {
+    let mut histogram = Histogram::new();
+    for &value in values {
+        histogram.add(value);
+    }
+    histogram
+}
+
+#[test]
+fn histogram_percentiles_match_definition() {
+    // Enough values for the histogram to switch from raw values to bins.
+    for count in [7, 100, 5000, 20_000] {
+        let values: Vec<i32> = (0..count).map(| index | (index * 7919 % 1999) - 999).collect();
+        let histogram = histogram(&values);
+        for per_mille in [0, 10, 70, 140, 250, 280, 500, 900, 950, 990, 999, 1000] {
+            assert_eq!(
+                histogram.percentile(per_mille as f64 / 10.),
+                Some(brute_force_percentile(&values, per_mille)),
+                "p{} of {count} values", per_mille as f64 / 10.,
+            );
+        }
+    }
+    assert_eq!(Histogram::new().percentile(50.), None);
+}
+
+#[test]
+fn whole_percentiles_of_a_hundred_values_are_exact_ranks() {
+    // 7. / 100. * 100. is 7.000000000000001 in floating point, which must not
+    // push the rank of p7 to the eighth value.
+    let mut values: Vec<i32> = (1..=99).map(| degrees | degrees * 10).collect();
+    values.push(999);
+    let histogram = histogram(&values);
+    assert_eq!(histogram.percentile(7.), Some(70));
+    assert_eq!(histogram.percentile(14.), Some(140));
+    assert_eq!(histogram.percentile(28.), Some(280));
+    assert_eq!(histogram.percentile(99.9), Some(999));
+}
+
+#[test]
+fn raw_values_and_bins_count_the_same() {
+    let values: Vec<i32> = (0..30_000).map(| index | (index * 31 % 1999) - 999).collect();
+    let mut merged = histogram(&values[..10]);
+    merged.merge(&histogram(&values[10..20_000]));
+    merged.merge(&histogram(&values[20_000..]));
+    assert_eq!(merged, histogram(&values));
+    assert_eq!(merged.count(), 30_000);
+
+    let mut small = histogram(&values[..5]);
+    small.merge(&histogram(&values[5..10]));
+    assert_eq!(small, histogram(&values[..10]));
+    assert_ne!(small, histogram(&values[..9]));
+}
+
+#[test]
+fn strategies_with_histograms_agree() {
+    let mut input = Vec::new();
+    Generator::new(30_000, 3).write(&mut input).unwrap();
+    let file = common::write_input(&input);
+    let path = file.path().to_str().unwrap();
+
+    let expected = MultithreadedRayon::new(1).histograms(true).aggregate(path).unwrap();
+    for station in &expected {
+        assert_eq!(station.histogram().map(Histogram::count), Some(station.count()));
+    }
+    for threads in [2, 5] {
+        let rayon = MultithreadedRayon::new(threads).histograms(true).aggregate(path).unwrap();
+        let streaming = Streaming::new(threads).histograms(true).aggregate(path).unwrap();
+        assert_eq!(rayon, expected, "rayon with {threads} threads");
+        assert_eq!(streaming, expected, "streaming with {threads} threads");
+    }
+}
+
+#[test]
+fn columns_parse_percentiles() {
+    assert_eq!("median".parse(), Ok(Column::Percentile(50.)));
+    assert_eq!("p99.9".parse(), Ok(Column::Percentile(99.9)));
+    assert_eq!("stddev".parse(), Ok(Column::Stddev));
+    assert!("p101".parse::<Column>().is_err());
+    assert!("pnan".parse::<Column>().is_err());
+    assert!("mode".parse::<Column>().is_err());
+}
+
+#[test]
+fn missing_histograms_print_empty_values() {
+    let file = common::write_input(b"Abha;1.0\n");
+    let stats = MultithreadedRayon::new(1).aggregate(file.path().to_str().unwrap()).unwrap();
+    let render = | format | {
+        let mut output = Vec::new();
+        write_stats(&stats, format, 1, &[Column::Percentile(95.)], &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+    assert_eq!(render(OutputFormat::Text), "{Abha=1.0/1.0/1.0/-}\n");
+    assert_eq!(render(OutputFormat::Csv), "station,min,mean,max,count,sum,p95\nAbha,1.0,1.0,1.0,1,1.0,\n");
+    assert!(render(OutputFormat::Json).contains(r#""p95":null"#));
+}
+
+#[test]
+fn cli_prints_requested_percentiles() {
+    let file = common::write_input(b"Abha;1.0\nAbha;3.0\nAbha;2.0\nAbha;10.0\nBern;-4.5\n");
+    let run = | strategy: &str | {
+        Command::new(env!("CARGO_BIN_EXE_brc"))
+            .arg(file.path())
+            .args(["--strategy", strategy, "--columns", "median,p95"])
+            .output()
+            .unwrap()
+    };
+    for strategy in ["multithreaded_rayon", "streaming"] {
+        let output = run(strategy);
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "{Abha=1.0/4.0/10.0/2.0/10.0, Bern=-4.5/-4.5/-4.5/-4.5/-4.5}\n",
+        );
+    }
+
+    let output = run("open_addressing");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not support percentiles"));
+}
@@ -0,0 +1,29 @@
+use brc::{Aggregator, Strategy};
+use brc::generate::Generator;
+use brc::segmented::Segmented;
+
+mod common;
+
+#[test]
+fn segment_sizes_agree_with_single_chunk() {
+    let mut input = Vec::new();
+    Generator::new(20_000, 9).write(&mut input).unwrap();
+    input.extend_from_slice(b"Abha;-1.5\r\nAbha;2.5");
+    let file = common::write_input(&input);
+    let expected = common::aggregate(Strategy::MultithreadedRayon, &file, 1);
+
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+    for segment_size in [1, 7, 64, 4096, 1 << 20] {
+        let stats = pool
+            .install(|| Segmented::new(segment_size).aggregate(file.path().to_str().unwrap()))
+            .unwrap();
+        assert_eq!(stats, expected, "segment size {segment_size}");
+    }
+}
+
+#[test]
+fn empty_file_with_tiny_segments() {
+    let file = common::write_input(b"");
+    let stats = Segmented::new(1).aggregate(file.path().to_str().unwrap()).unwrap();
+    assert!(stats.is_empty());
+}
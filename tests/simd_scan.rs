@@ -0,0 +1,50 @@
+use brc::{Aggregator, Strategy};
+use brc::generate::Generator;
+use brc::simd_scan::{Scanner, SimdScan};
+
+mod common;
+
+const SCANNERS: [Scanner; 3] = [Scanner::Avx2, Scanner::Sse2, Scanner::Portable];
+
+fn assert_scanners_agree(input: &[u8]) {
+    let file = common::write_input(input);
+    let expected = common::aggregate(Strategy::MultithreadedRayon, &file, 3);
+    for scanner in SCANNERS.into_iter().filter(| scanner | scanner.is_supported()) {
+        for threads in [1, 3, 8] {
+            let stats = SimdScan::new(threads)
+                .scanner(scanner)
+                .aggregate(file.path().to_str().unwrap())
+                .unwrap();
+            assert_eq!(stats, expected, "{scanner:?} with {threads} threads");
+        }
+    }
+}
+
+#[test]
+fn scanners_agree_on_generated_input() {
+    let mut input = Vec::new();
+    Generator::new(100_000, 5).unique_stations(2_000).write(&mut input).unwrap();
+    assert_scanners_agree(&input);
+}
+
+#[test]
+fn scanners_agree_on_line_endings_and_tails() {
+    assert_scanners_agree(b"");
+    assert_scanners_agree(b"Abha;-1.5");
+    assert_scanners_agree(b"Abha;-1.5\r\nBosaso;22.0\r\nAbha;3.0\r\n");
+    assert_scanners_agree(b"Abha;1.0\nAbha;-2.0\nAbha;3.0\r\nAbha;-4.0");
+}
+
+#[test]
+fn records_across_block_boundaries() {
+    for padding in 0..64 {
+        let input = format!("{};1.0\nKunming;-9.9\nKunming;0.5\n", "x".repeat(padding));
+        assert_scanners_agree(input.as_bytes());
+    }
+}
+
+#[test]
+fn portable_scanner_is_always_supported() {
+    assert!(Scanner::Portable.is_supported());
+    assert!(Scanner::detect().is_supported());
+}
@@ -0,0 +1,65 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+use brc::Strategy;
+use brc::format::write_output;
+use brc::generate::Generator;
+use brc::streaming::brc_reader;
+
+mod common;
+
+/// Hands out at most a few bytes per read, like a slow pipe.
+struct Trickle<'a> {
+    data: &'a [u8],
+    step: usize,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        self.step = self.step % 7 + 1;
+        let length = self.step.min(buffer.len()).min(self.data.len());
+        buffer[..length].copy_from_slice(&self.data[..length]);
+        self.data = &self.data[length..];
+        Ok(length)
+    }
+}
+
+#[test]
+fn matches_mmap_strategies_across_blocks() {
+    let mut input = Vec::new();
+    Generator::new(700_000, 4).write(&mut input).unwrap();
+    input.extend_from_slice(b"Abha;-1.5\r\nAbha;2.5");
+    let expected = common::aggregate(Strategy::MultithreadedRayon, &common::write_input(&input), 2);
+
+    for threads in [1, 3] {
+        assert_eq!(brc_reader(input.as_slice(), threads).unwrap(), expected);
+    }
+}
+
+#[test]
+fn small_reads_and_byte_order_mark() {
+    let input = b"\xEF\xBB\xBFKunming;19.8\nBosaso;-5.0\r\nKunming;-1.2";
+    let expected = common::aggregate(Strategy::MultithreadedRayon, &common::write_input(input), 1);
+    let stats = brc_reader(Trickle { data: input, step: 0 }, 2).unwrap();
+    assert_eq!(stats, expected);
+    assert!(brc_reader(&b""[..], 2).unwrap().is_empty());
+}
+
+#[test]
+fn cli_reads_stdin_for_dash() {
+    let input = b"Kunming;19.8\nBosaso;-5.0\nKunming;-1.2\n";
+    let mut child = Command::new(env!("CARGO_BIN_EXE_brc"))
+        .args(["-", "--threads", "2"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let mut expected = Vec::new();
+    let stats = common::aggregate(Strategy::MultithreadedRayon, &common::write_input(input), 1);
+    write_output(&stats, &mut expected).unwrap();
+    assert_eq!(output.stdout, expected);
+}
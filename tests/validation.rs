@@ -0,0 +1,53 @@
+use brc::Aggregator;
+use brc::error::{RecordError, RecordErrorKind};
+use brc::validating::{Validating, Validation, parse_record};
+
+mod common;
+
+const INPUT: &[u8] = b"Hamburg;12.0\nBulawayo;8.9\nPalembang;3x.8\nHamburg;-3.4\nNoSeparator\n";
+
+#[test]
+fn strict_reports_first_malformed_line() {
+    let file = common::write_input(INPUT);
+
+    for threads in [1, 2, 4] {
+        let error = Validating::new(Validation::Strict, threads)
+            .aggregate(file.path().to_str().unwrap())
+            .unwrap_err();
+        let error = error.downcast_ref::<RecordError>().unwrap();
+        assert_eq!(*error, RecordError { kind: RecordErrorKind::InvalidValue, offset: 26, line: 3 });
+    }
+}
+
+#[test]
+fn lenient_skips_and_counts_malformed_lines() {
+    let file = common::write_input(INPUT);
+
+    let stats = Validating::new(Validation::Lenient, 2)
+        .aggregate(file.path().to_str().unwrap())
+        .unwrap();
+    assert_eq!(stats.rejected(), 2);
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats.get("Hamburg").unwrap().count(), 2);
+}
+
+#[test]
+fn parse_record_rejects_malformed_records() {
+    let long_name = format!("{};1.0", "x".repeat(101));
+    let cases: [(&[u8], RecordErrorKind); 8] = [
+        (b"Hamburg 12.0", RecordErrorKind::MissingSeparator),
+        (b";12.0", RecordErrorKind::EmptyName),
+        (long_name.as_bytes(), RecordErrorKind::NameTooLong(101)),
+        (b"Ham\xffburg;12.0", RecordErrorKind::InvalidUtf8),
+        (b"Hamburg;12", RecordErrorKind::InvalidValue),
+        (b"Hamburg;1.25", RecordErrorKind::InvalidValue),
+        (b"Hamburg;-a.0", RecordErrorKind::InvalidValue),
+        (b"Hamburg;100.0", RecordErrorKind::ValueOutOfRange),
+    ];
+
+    for (line, kind) in cases {
+        assert_eq!(parse_record(line), Err(kind), "{}", String::from_utf8_lossy(line));
+    }
+    assert_eq!(parse_record(b"Hamburg;-99.9"), Ok((&b"Hamburg"[..], -999)));
+    assert_eq!(parse_record(b"Hamburg;5.0"), Ok((&b"Hamburg"[..], 50)));
+}
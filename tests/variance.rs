@@ -0,0 +1,111 @@
+use brc::{Measurement, Strategy};
+use brc::format::{Column, OutputFormat, write_stats};
+use brc::generate::Generator;
+use clap::ValueEnum;
+
+mod common;
+
+/// Two-pass population variance of `values` in tenths, in square degrees.
+fn two_pass_variance(values: &[i32]) -> f64 {
+    let degrees: Vec<f64> = values.iter().map(| &value | value as f64 / 10.).collect();
+    let mean = degrees.iter().sum::<f64>() / degrees.len() as f64;
+    degrees.iter().map(| value | (value - mean).powi(2)).sum::<f64>() / degrees.len() as f64
+}
+
+fn measurement(values: &[i32]) -> Measurement {
+    let mut measurement = Measurement::new(values[0]);
+    for &value in &values[1..] {
+        measurement.update(value);
+    }
+    measurement
+}
+
+#[test]
+fn variance_matches_two_pass() {
+    let values = [-999, 999, 0, 125, -37, 42, 42, 980];
+    let variance = measurement(&values).variance() / 100.;
+    assert!((variance - two_pass_variance(&values)).abs() < 1e-9, "{variance}");
+    assert_eq!(measurement(&[153]).variance(), 0.);
+}
+
+#[test]
+fn merge_matches_single_pass() {
+    let values: Vec<i32> = (0..1000).map(| index | (index * 37 % 1999) - 999).collect();
+    let mut merged = measurement(&values[..123]);
+    merged.merge(&measurement(&values[123..700]));
+    merged.merge(&measurement(&values[700..]));
+    assert_eq!(merged, measurement(&values));
+}
+
+#[test]
+fn large_mean_keeps_small_spread() {
+    // A 0.05 degree spread around 99.85, where the float textbook formula
+    // subtracts two nearly equal sums, stays exact with integer sums.
+    let mut measurement = Measurement::new(999);
+    for index in 1..10_000_000 {
+        measurement.update(999 - index % 2);
+    }
+    assert!((measurement.variance() - 0.25).abs() < 1e-9, "{}", measurement.variance());
+}
+
+#[test]
+fn strategies_agree_on_variance() {
+    let mut input = Vec::new();
+    Generator::new(20_000, 7).write(&mut input).unwrap();
+    let file = common::write_input(&input);
+    let expected = common::aggregate(Strategy::MultithreadedRayon, &file, 1);
+    for &strategy in Strategy::value_variants() {
+        for threads in [1, 3] {
+            let stats = common::aggregate(strategy, &file, threads);
+            let sums_of_squares = | stats: &brc::StationStats | -> Vec<u64> {
+                stats.iter().map(| station | station.measurement().sum_of_squares).collect()
+            };
+            assert_eq!(
+                sums_of_squares(&stats), sums_of_squares(&expected),
+                "{strategy:?} with {threads} threads",
+            );
+        }
+    }
+}
+
+fn render(format: OutputFormat, columns: &[Column]) -> String {
+    let file = common::write_input(b"Abha;1.0\nAbha;3.0\nAbha;2.0\nBern;-4.5\nCabo;0.0\nCabo;0.1\n");
+    let stats = common::aggregate(Strategy::MultithreadedRayon, &file, 1);
+    let mut output = Vec::new();
+    write_stats(&stats, format, 3, columns, &mut output).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn extra_columns_in_every_format() {
+    let columns = [Column::Variance, Column::Stddev];
+    assert_eq!(
+        render(OutputFormat::Text, &columns),
+        "{Abha=1.0/2.0/3.0/0.6666666666666667/0.816496580927726, \
+         Bern=-4.5/-4.5/-4.5/0.0/0.0, Cabo=0.0/0.1/0.1/0.0025/0.05}\n",
+    );
+    assert_eq!(
+        render(OutputFormat::Csv, &columns[1..]),
+        "station,min,mean,max,count,sum,stddev\n\
+         Abha,1.000,2.000,3.000,3,6.000,0.816\n\
+         Bern,-4.500,-4.500,-4.500,1,-4.500,0.000\n\
+         Cabo,0.000,0.050,0.100,2,0.100,0.050\n",
+    );
+
+    // JSON has no precision option, so the extra columns keep every digit.
+    let json: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json, &columns)).unwrap();
+    assert_eq!(json["Cabo"]["variance"], serde_json::json!(0.0025));
+    assert_eq!(json["Cabo"]["stddev"], serde_json::json!(0.05));
+    assert!((json["Abha"]["variance"].as_f64().unwrap() - 2. / 3.).abs() < 1e-15);
+    assert!((json["Abha"]["stddev"].as_f64().unwrap() - (2f64 / 3.).sqrt()).abs() < 1e-15);
+    let line = render(OutputFormat::Jsonl, &columns[..1]);
+    let expected = concat!(
+        r#"{"station":"Abha","min":1.0,"mean":2.0,"max":3.0,"count":3,"sum":6.0,"#,
+        r#""variance":0.6666666666666667}"#,
+    );
+    assert!(line.starts_with(expected), "{line}");
+    assert_eq!(
+        render(OutputFormat::Text, &[]),
+        "{Abha=1.0/2.0/3.0, Bern=-4.5/-4.5/-4.5, Cabo=0.0/0.1/0.1}\n",
+    );
+}